@@ -0,0 +1,114 @@
+use database::{ConcurrentTree, Node, RootNode};
+use std::{
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+const COUNT: u64 = 200_000;
+const ROUNDS: usize = 5;
+
+fn keys() -> Vec<u64> {
+    (0..COUNT).map(|i| (i * 48271 + 11) % COUNT).collect()
+}
+
+fn report(name: &str, elapsed: Duration) {
+    println!(
+        "{:<36} {:>10.2} ms {:>12.0} ops/s",
+        name,
+        elapsed.as_secs_f64() * 1000.0,
+        COUNT as f64 / elapsed.as_secs_f64()
+    );
+}
+
+/// Splits `keys` among `threads` workers, runs `f` on each share against a
+/// fresh state and reports the fastest of a few rounds.
+fn measure<T, S, F>(name: &str, keys: &[u64], threads: usize, setup: S, f: F)
+where
+    T: Sync,
+    S: Fn() -> T,
+    F: Fn(&T, &[u64]) + Sync,
+{
+    let mut best = Duration::MAX;
+    for _ in 0..ROUNDS {
+        let state = setup();
+        let start = Instant::now();
+        thread::scope(|scope| {
+            for share in keys.chunks(keys.len().div_ceil(threads)) {
+                let (state, f) = (&state, &f);
+                scope.spawn(move || f(state, share));
+            }
+        });
+        best = best.min(start.elapsed());
+    }
+    report(&format!("{} x{}", name, threads), best);
+}
+
+fn bench(keys: &[u64], threads: usize) {
+    let filled_tree = || {
+        let tree = ConcurrentTree::<u64, u64>::new();
+        for key in keys {
+            tree.insert(key, *key).unwrap();
+        }
+        tree
+    };
+    let filled_mutex = || {
+        let mut index = RootNode::<u64, u64>::new();
+        for key in keys {
+            index.insert(key, *key).unwrap();
+        }
+        Mutex::new(index)
+    };
+
+    measure(
+        "ConcurrentTree<32> insert",
+        keys,
+        threads,
+        ConcurrentTree::<u64, u64>::new,
+        |tree, share| {
+            for key in share {
+                tree.insert(key, *key).unwrap();
+            }
+        },
+    );
+    measure(
+        "Mutex<RootNode<32>> insert",
+        keys,
+        threads,
+        || Mutex::new(RootNode::<u64, u64>::new()),
+        |index, share| {
+            for key in share {
+                index.lock().unwrap().insert(key, *key).unwrap();
+            }
+        },
+    );
+    measure(
+        "ConcurrentTree<32> find",
+        keys,
+        threads,
+        filled_tree,
+        |tree, share| {
+            for key in share {
+                assert_eq!(tree.find(key), Some(*key));
+            }
+        },
+    );
+    measure(
+        "Mutex<RootNode<32>> find",
+        keys,
+        threads,
+        filled_mutex,
+        |index, share| {
+            for key in share {
+                assert_eq!(index.lock().unwrap().find(key), Some(key));
+            }
+        },
+    );
+}
+
+fn main() {
+    let keys = keys();
+    for threads in [1, 2, 4, 8] {
+        bench(&keys, threads);
+    }
+}
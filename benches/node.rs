@@ -0,0 +1,101 @@
+//! Times inserts and lookups of scattered keys in `RootNode` at a few
+//! fanouts, with `BTreeMap` as the reference point. Only the current tree is
+//! measured; compare runs of this bench on the same machine across commits
+//! rather than against figures recorded elsewhere.
+
+use database::{Fanout, Node, RootNode};
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+const COUNT: u64 = 200_000;
+const ROUNDS: usize = 5;
+
+fn keys() -> Vec<u64> {
+    // A full-period linear congruential sequence, so every key is distinct
+    // and arrives in a scattered order.
+    (0..COUNT).map(|i| (i * 48271 + 11) % COUNT).collect()
+}
+
+fn report(name: &str, elapsed: Duration) {
+    println!(
+        "{:<24} {:>10.2} ms {:>12.0} ops/s",
+        name,
+        elapsed.as_secs_f64() * 1000.0,
+        COUNT as f64 / elapsed.as_secs_f64()
+    );
+}
+
+/// Runs `f` a few times on a fresh state and reports the fastest round, which
+/// is the least disturbed by whatever else the machine is doing.
+fn measure<T, S, F>(name: &str, setup: S, mut f: F)
+where
+    S: Fn() -> T,
+    F: FnMut(&mut T),
+{
+    let mut best = Duration::MAX;
+    for _ in 0..ROUNDS {
+        let mut state = setup();
+        let start = Instant::now();
+        f(&mut state);
+        best = best.min(start.elapsed());
+    }
+    report(name, best);
+}
+
+fn bench_root_node(keys: &[u64], leaf: usize) {
+    let fanout = Fanout::new(leaf, leaf + 1);
+    let filled = || {
+        let mut index = RootNode::<u64, u64>::with_fanout(fanout);
+        for key in keys {
+            index.insert(key, *key).unwrap();
+        }
+        index
+    };
+
+    measure(
+        &format!("RootNode<{}> insert", leaf),
+        || RootNode::<u64, u64>::with_fanout(fanout),
+        |index| {
+            for key in keys {
+                index.insert(key, *key).unwrap();
+            }
+        },
+    );
+    println!(
+        "{:<24} {}",
+        format!("RootNode<{}> shape", leaf),
+        filled().stats()
+    );
+    measure(&format!("RootNode<{}> find", leaf), filled, |index| {
+        let sum: u64 = keys.iter().map(|key| index.find(key).unwrap()).sum();
+        assert_eq!(sum, (0..COUNT).sum::<u64>());
+    });
+}
+
+fn bench_btree_map(keys: &[u64]) {
+    let filled = || {
+        keys.iter()
+            .map(|key| (*key, *key))
+            .collect::<BTreeMap<_, _>>()
+    };
+
+    measure("BTreeMap insert", BTreeMap::new, |index| {
+        for key in keys {
+            index.insert(*key, *key);
+        }
+    });
+    measure("BTreeMap find", filled, |index| {
+        let sum: u64 = keys.iter().map(|key| index.get(key).unwrap()).sum();
+        assert_eq!(sum, (0..COUNT).sum::<u64>());
+    });
+}
+
+fn main() {
+    let keys = keys();
+    bench_root_node(&keys, 8);
+    bench_root_node(&keys, 32);
+    bench_root_node(&keys, 128);
+    bench_btree_map(&keys);
+}
@@ -0,0 +1,128 @@
+use database::{Fanout, PrefixTree, RootNode};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+const COUNT: usize = 200_000;
+
+/// Forwards to the system allocator and keeps track of the bytes currently
+/// allocated, so the footprint of a tree is what it leaves allocated.
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Paths of the kind a file index holds: a long shared root, a handful of
+/// directories and numbered files in each.
+fn keys() -> Vec<String> {
+    (0..COUNT)
+        .map(|i| (i * 48271 + 11) % COUNT)
+        .map(|i| {
+            format!(
+                "/srv/database/tables/customers/partition-{:02}/segment-{:04}/row-{:08}",
+                i % 16,
+                i / 16 % 1000,
+                i
+            )
+        })
+        .collect()
+}
+
+/// Bytes left allocated by a tree that `build` fills with every key, the
+/// time that took, and the bytes left once `shrink` removed three keys out
+/// of four again, which merges most nodes.
+fn footprint<T, B, S>(build: B, shrink: S) -> (usize, Duration, usize)
+where
+    B: FnOnce() -> T,
+    S: FnOnce(&mut T),
+{
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let started = Instant::now();
+    let mut built = build();
+    let elapsed = started.elapsed();
+    let full = ALLOCATED.load(Ordering::Relaxed) - before;
+    shrink(&mut built);
+    let shrunk = ALLOCATED.load(Ordering::Relaxed) - before;
+    drop(built);
+    (full, elapsed, shrunk)
+}
+
+fn report(name: &str, (full, elapsed, shrunk): (usize, Duration, usize), raw: usize) {
+    println!(
+        "{:<24} {:>10.2} MiB {:>8.1} bytes/entry {:>6.2}x key bytes {:>8.1} ms to insert {:>8.2} MiB after removals",
+        name,
+        full as f64 / (1024.0 * 1024.0),
+        full as f64 / COUNT as f64,
+        full as f64 / raw as f64,
+        elapsed.as_secs_f64() * 1000.0,
+        shrunk as f64 / (1024.0 * 1024.0)
+    );
+}
+
+fn main() {
+    let keys = keys();
+    let raw: usize = keys.iter().map(String::len).sum();
+    println!(
+        "{} keys, {:.1} bytes each on average",
+        COUNT,
+        raw as f64 / COUNT as f64
+    );
+    let removed = || keys.iter().enumerate().filter(|(idx, _)| idx % 4 != 0);
+
+    for leaf in [16, 32, 64] {
+        let fanout = Fanout::new(leaf, leaf + 1);
+        let plain = footprint(
+            || {
+                let mut index = RootNode::<String, u64>::with_fanout(fanout);
+                for (idx, key) in keys.iter().enumerate() {
+                    index.insert(key, idx as u64).unwrap();
+                }
+                index
+            },
+            |index| {
+                for (_, key) in removed() {
+                    index.remove(key).unwrap();
+                }
+            },
+        );
+        report(&format!("RootNode<{}>", leaf), plain, raw);
+
+        let compressed = footprint(
+            || {
+                let mut index = PrefixTree::<String, u64>::with_fanout(fanout);
+                for (idx, key) in keys.iter().enumerate() {
+                    index.insert(key, idx as u64).unwrap();
+                }
+                index
+            },
+            |index| {
+                for (_, key) in removed() {
+                    index.remove(key).unwrap();
+                }
+            },
+        );
+        report(&format!("PrefixTree<{}>", leaf), compressed, raw);
+        println!(
+            "{:<24} {:>9.1} % {:>63.1} %",
+            "saved",
+            100.0 * (plain.0 - compressed.0) as f64 / plain.0 as f64,
+            100.0 * (plain.2 - compressed.2) as f64 / plain.2 as f64
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use crate::node::{Comparator, RootNode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{fmt, fmt::Write as _, ops::Bound};
+
+#[derive(thiserror::Error, Debug)]
+pub enum CursorError {
+    #[error("cursor token is malformed")]
+    InvalidToken,
+}
+
+/// Ordered entries a cursor can page through.
+pub trait Paginate<K, V> {
+    /// Up to `size` entries within `bounds` in key order, taken from the
+    /// start of the bounds, or from the end if `reverse` is set.
+    fn page_within(
+        &self,
+        bounds: (Bound<&K>, Bound<&K>),
+        size: usize,
+        reverse: bool,
+    ) -> Vec<(K, V)>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+enum Position<K> {
+    Start,
+    Before(K),
+    After(K),
+    End,
+}
+
+/// Position between two keys of an index. A cursor holds a key rather than
+/// an offset, so it keeps pointing at the same place while entries are
+/// inserted or removed around it, including the key itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cursor<K> {
+    position: Position<K>,
+}
+
+/// Entries read by a cursor in key order, along with the cursors before the
+/// first and after the last of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<K, V> {
+    pub entries: Vec<(K, V)>,
+    /// Cursor to page backward from.
+    pub start: Cursor<K>,
+    /// Cursor to page forward from.
+    pub end: Cursor<K>,
+}
+
+impl<K> Cursor<K> {
+    /// Cursor before the first entry.
+    pub fn start() -> Self {
+        Cursor {
+            position: Position::Start,
+        }
+    }
+
+    /// Cursor after the last entry.
+    pub fn end() -> Self {
+        Cursor {
+            position: Position::End,
+        }
+    }
+
+    /// Cursor right before `key`, so that the next page begins at it.
+    pub fn seek(key: K) -> Self {
+        Cursor {
+            position: Position::Before(key),
+        }
+    }
+
+    /// Cursor right after `key`, so that the previous page ends at it.
+    pub fn seek_after(key: K) -> Self {
+        Cursor {
+            position: Position::After(key),
+        }
+    }
+
+    /// Reads up to `size` entries after this cursor.
+    pub fn next_page<V, P>(&self, source: &P, size: usize) -> Page<K, V>
+    where
+        K: Clone,
+        P: Paginate<K, V>,
+    {
+        let start = match &self.position {
+            Position::Start => Bound::Unbounded,
+            Position::Before(key) => Bound::Included(key),
+            Position::After(key) => Bound::Excluded(key),
+            Position::End => return self.empty_page(),
+        };
+        let entries = source.page_within((start, Bound::Unbounded), size, false);
+        self.page(entries)
+    }
+
+    /// Reads up to `size` entries before this cursor.
+    pub fn prev_page<V, P>(&self, source: &P, size: usize) -> Page<K, V>
+    where
+        K: Clone,
+        P: Paginate<K, V>,
+    {
+        let end = match &self.position {
+            Position::Start => return self.empty_page(),
+            Position::Before(key) => Bound::Excluded(key),
+            Position::After(key) => Bound::Included(key),
+            Position::End => Bound::Unbounded,
+        };
+        let entries = source.page_within((Bound::Unbounded, end), size, true);
+        self.page(entries)
+    }
+
+    fn empty_page<V>(&self) -> Page<K, V>
+    where
+        K: Clone,
+    {
+        self.page(Vec::new())
+    }
+
+    fn page<V>(&self, entries: Vec<(K, V)>) -> Page<K, V>
+    where
+        K: Clone,
+    {
+        let start = match entries.first() {
+            Some((key, _)) => Cursor::seek(key.clone()),
+            None => self.clone(),
+        };
+        let end = match entries.last() {
+            Some((key, _)) => Cursor::seek_after(key.clone()),
+            None => self.clone(),
+        };
+        Page {
+            entries,
+            start,
+            end,
+        }
+    }
+
+    /// Encodes this cursor as an opaque token to hand out to clients.
+    pub fn to_token(&self) -> Result<String, serde_json::Error>
+    where
+        K: Serialize,
+    {
+        let json = serde_json::to_vec(self)?;
+        let mut token = String::with_capacity(json.len() * 2);
+        for byte in json {
+            // Writing to a `String` does not fail.
+            let _ = write!(token, "{:02x}", byte);
+        }
+        Ok(token)
+    }
+
+    /// Decodes a token made by `to_token`.
+    pub fn from_token(token: &str) -> Result<Self, CursorError>
+    where
+        K: DeserializeOwned,
+    {
+        if !token.len().is_multiple_of(2) || !token.is_ascii() {
+            return Err(CursorError::InvalidToken);
+        }
+        let json = (0..token.len())
+            .step_by(2)
+            .map(|idx| u8::from_str_radix(&token[idx..idx + 2], 16))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| CursorError::InvalidToken)?;
+        serde_json::from_slice(&json).map_err(|_| CursorError::InvalidToken)
+    }
+}
+
+impl<K, V, C> Paginate<K, V> for RootNode<K, V, C>
+where
+    K: 'static + fmt::Debug + Clone + Ord,
+    V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone,
+{
+    fn page_within(
+        &self,
+        bounds: (Bound<&K>, Bound<&K>),
+        size: usize,
+        reverse: bool,
+    ) -> Vec<(K, V)> {
+        let range = self
+            .range(bounds)
+            .map(|(key, value)| (key.clone(), value.clone()));
+        if reverse {
+            let mut entries: Vec<_> = range.rev().take(size).collect();
+            entries.reverse();
+            entries
+        } else {
+            range.take(size).collect()
+        }
+    }
+}
@@ -9,12 +9,76 @@ use std::{
     time::SystemTime,
 };
 
+/// First bytes of every file written in the versioned format. Files without
+/// them are read as the legacy layout of a length, a SHA-512 hash and JSON.
+const MAGIC: [u8; 4] = *b"DBRC";
+const VERSION: u8 = 1;
+
 #[derive(thiserror::Error, Debug)]
 pub enum IOError {
     #[error("hash is not matched")]
     HashMismatch,
     #[error("file size is not matched")]
     FileSizeMismatch,
+    #[error("format version {0} is not supported")]
+    UnsupportedVersion(u8),
+    #[error("payload format {0} is unknown")]
+    UnknownFormat(u8),
+}
+
+/// Encoding of the payload of dump and log files. Both are read back
+/// regardless of the format they are written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// Compact binary encoding, the default.
+    #[default]
+    Binary,
+    /// Human readable JSON, meant for debugging.
+    Json,
+}
+
+impl Format {
+    pub(crate) fn tag(self) -> u8 {
+        match self {
+            Format::Binary => 0,
+            Format::Json => 1,
+        }
+    }
+
+    pub(crate) fn from_tag(tag: u8) -> Result<Self, IOError> {
+        match tag {
+            0 => Ok(Format::Binary),
+            1 => Ok(Format::Json),
+            _ => Err(IOError::UnknownFormat(tag)),
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Binary => "bin",
+            Format::Json => "json",
+        }
+    }
+
+    pub fn encode<T>(self, value: &T) -> Result<Vec<u8>, Box<dyn Error>>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(match self {
+            Format::Binary => bincode::serialize(value)?,
+            Format::Json => serde_json::to_vec(value)?,
+        })
+    }
+
+    pub fn decode<T>(self, payload: &[u8]) -> Result<T, Box<dyn Error>>
+    where
+        T: DeserializeOwned,
+    {
+        Ok(match self {
+            Format::Binary => bincode::deserialize(payload)?,
+            Format::Json => serde_json::from_slice(payload)?,
+        })
+    }
 }
 
 fn now() -> Result<u128, Box<dyn Error>> {
@@ -32,58 +96,95 @@ where
     hasher.finalize().as_ref().to_vec()
 }
 
-pub fn dump<T>(folder_path: &Path, value: &T) -> Result<PathBuf, Box<dyn Error>>
+/// Writes `value` to a new file in `folder_path`, behind a header of the
+/// magic, the format version, the payload format, the payload length and a
+/// CRC-32 of the payload.
+pub fn dump<T>(folder_path: &Path, value: &T, format: Format) -> Result<PathBuf, Box<dyn Error>>
 where
     T: ?Sized + Serialize,
 {
-    let json = serde_json::to_string(value)?;
-    let json_len = json.len() as u64;
-    let json_hash = hash(&json);
+    let payload = format.encode(value)?;
 
     fs::create_dir_all(folder_path)?;
-    let file_path = folder_path.join(format!("{}.json", now()?));
-    let mut f = fs::File::create(&file_path)?;
+    let file_path = folder_path.join(format!("{}.{}", now()?, format.extension()));
+    let mut f = io::BufWriter::new(fs::File::create(&file_path)?);
 
-    f.write_u64::<LittleEndian>(json_len)?;
-    io::Write::write_all(&mut f, &json_hash)?;
-    io::Write::write_all(&mut f, json.as_bytes())?;
-    f.sync_all()?;
+    io::Write::write_all(&mut f, &MAGIC)?;
+    f.write_u8(VERSION)?;
+    f.write_u8(format.tag())?;
+    f.write_u64::<LittleEndian>(payload.len() as u64)?;
+    f.write_u32::<LittleEndian>(crc32fast::hash(&payload))?;
+    io::Write::write_all(&mut f, &payload)?;
+    f.into_inner().map_err(|e| e.into_error())?.sync_all()?;
 
     Ok(file_path)
 }
 
-pub fn load<T>(file_path: &Path) -> Result<T, Box<dyn Error>>
-where
-    T: DeserializeOwned,
-{
-    let f = fs::File::open(file_path)?;
-    let mut buf_reader = io::BufReader::new(f);
+/// Reads the payload of a file written by `dump` or in the legacy layout,
+/// along with the format it is encoded in.
+pub fn read(file_path: &Path) -> Result<(Format, Vec<u8>), Box<dyn Error>> {
+    let mut bytes = Vec::new();
+    fs::File::open(file_path)?.read_to_end(&mut bytes)?;
 
-    let json_len = buf_reader.read_u64::<LittleEndian>()? as usize;
-    let mut json_hash = [0u8; 64];
-    let mut json_string = String::new();
+    if !bytes.starts_with(&MAGIC) {
+        return read_legacy(&bytes).map(|payload| (Format::Json, payload));
+    }
+    let mut bytes = &bytes[MAGIC.len()..];
+    let version = bytes.read_u8()?;
+    if version != VERSION {
+        return Err(Box::new(IOError::UnsupportedVersion(version)));
+    }
+    let format = Format::from_tag(bytes.read_u8()?)?;
+    let len = bytes.read_u64::<LittleEndian>()?;
+    let checksum = bytes.read_u32::<LittleEndian>()?;
+
+    if len != bytes.len() as u64 {
+        Err(Box::new(IOError::FileSizeMismatch))
+    } else if checksum != crc32fast::hash(bytes) {
+        Err(Box::new(IOError::HashMismatch))
+    } else {
+        Ok((format, bytes.to_vec()))
+    }
+}
 
-    buf_reader.read_exact(&mut json_hash)?;
-    buf_reader.read_to_string(&mut json_string)?;
+fn read_legacy(mut bytes: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let json_len = bytes.read_u64::<LittleEndian>()? as usize;
+    let mut json_hash = [0u8; 64];
+    bytes.read_exact(&mut json_hash)?;
 
-    if json_len != json_string.len() {
+    if json_len != bytes.len() {
         Err(Box::new(IOError::FileSizeMismatch))
-    } else if json_hash != hash(&json_string).as_slice() {
+    } else if json_hash != hash(bytes).as_slice() {
         Err(Box::new(IOError::HashMismatch))
     } else {
-        let value: T = serde_json::from_str(&json_string)?;
-        Ok(value)
+        Ok(bytes.to_vec())
     }
 }
 
-pub fn remove_dir(folder_path: &Path) -> Result<(), Box<dyn Error>> {
-    if let Err(e) = fs::remove_dir_all(&folder_path) {
-        if let std::io::ErrorKind::NotFound = e.kind() {
-            Ok(())
-        } else {
-            Err(Box::new(e))
-        }
+/// Whether `error`, returned by `read`, means that the file was only partly
+/// written, as a crash in the middle of `dump` leaves it.
+pub fn is_torn(error: &(dyn Error + 'static)) -> bool {
+    if let Some(error) = error.downcast_ref::<io::Error>() {
+        error.kind() == io::ErrorKind::UnexpectedEof
     } else {
-        Ok(())
+        matches!(
+            error.downcast_ref::<IOError>(),
+            Some(IOError::HashMismatch) | Some(IOError::FileSizeMismatch)
+        )
     }
 }
+
+pub fn load<T>(file_path: &Path) -> Result<T, Box<dyn Error>>
+where
+    T: DeserializeOwned,
+{
+    let (format, payload) = read(file_path)?;
+    format.decode(&payload)
+}
+
+/// Makes the creation, renaming and removal of files in `folder_path`
+/// durable.
+pub fn sync_dir(folder_path: &Path) -> Result<(), Box<dyn Error>> {
+    fs::File::open(folder_path)?.sync_all()?;
+    Ok(())
+}
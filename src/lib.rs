@@ -1,14 +1,29 @@
+mod cursor;
 mod io;
 mod node;
+mod paged;
 mod persistence;
 mod table;
 mod tests;
 mod transaction;
+mod wal;
 
-pub use node::{Node, RootNode};
-pub use persistence::{dump, load};
-pub use table::{DefaultSecondaryIndex, Primitive, Table};
+pub use cursor::{Cursor, CursorError, Page, Paginate};
+pub use io::Format;
+pub use node::{
+    ByteKey, CaseInsensitive, CheckReport, Collation, Comparator, ConcurrentTree, Entry, Fanout,
+    IntoIter, MultiKey, MultiMap, Natural, Node, NodeError, OccupiedEntry, PrefixRange, PrefixTree,
+    Range, RootNode, Snapshot, TreeStats, VacantEntry, Violation,
+};
+pub use paged::{PagedError, PagedRange, PagedTree};
+pub use persistence::{
+    checkpoint, dump, dump_as, load, load_collated, recover, PersistenceError, Recovery,
+};
+pub use table::{DefaultSecondaryIndex, Primitive, Table, TableSnapshot};
 pub use transaction::{Request, Transaction};
+pub use wal::{CheckpointToken, TornTail, Wal, WalError};
 
 const WAL_FOLDER_PATH: &str = "commit";
-const DUMP_FILE_PATH: &str = "full_dump.json";
+const DUMP_FILE_PATH: &str = "full_dump";
+/// Dump file written by versions before the binary format.
+const LEGACY_DUMP_FILE_PATH: &str = "full_dump.json";
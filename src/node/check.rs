@@ -0,0 +1,161 @@
+use std::fmt;
+
+/// Result of walking a tree with `RootNode::check`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckReport<K> {
+    pub violations: Vec<Violation<K>>,
+    pub entries: usize,
+    pub height: usize,
+}
+
+/// A broken structural invariant. `path` holds the child indices leading
+/// from the root to the offending node.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation<K> {
+    UnsortedKeys {
+        path: Vec<usize>,
+    },
+    SeparatorMismatch {
+        path: Vec<usize>,
+        separator: K,
+        last_key: Option<K>,
+    },
+    Overflow {
+        path: Vec<usize>,
+        len: usize,
+        capacity: usize,
+    },
+    Underflow {
+        path: Vec<usize>,
+        len: usize,
+        minimum: usize,
+    },
+    UnevenDepth {
+        path: Vec<usize>,
+        depth: usize,
+        expected: usize,
+    },
+    LengthMismatch {
+        path: Vec<usize>,
+        recorded: usize,
+        actual: usize,
+    },
+}
+
+impl<K> CheckReport<K> {
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl<K> fmt::Display for CheckReport<K>
+where
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} entries, height {}, {} violation(s)",
+            self.entries,
+            self.height,
+            self.violations.len()
+        )?;
+        for violation in self.violations.iter() {
+            writeln!(f, "  {}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+impl<K> fmt::Display for Violation<K>
+where
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::UnsortedKeys { path } => write!(f, "{:?}: keys out of order", path),
+            Violation::SeparatorMismatch {
+                path,
+                separator,
+                last_key,
+            } => write!(
+                f,
+                "{:?}: separator {:?} does not match last key {:?}",
+                path, separator, last_key
+            ),
+            Violation::Overflow {
+                path,
+                len,
+                capacity,
+            } => write!(
+                f,
+                "{:?}: {} entries exceed capacity {}",
+                path, len, capacity
+            ),
+            Violation::Underflow { path, len, minimum } => {
+                write!(f, "{:?}: {} entries below minimum {}", path, len, minimum)
+            }
+            Violation::UnevenDepth {
+                path,
+                depth,
+                expected,
+            } => write!(
+                f,
+                "{:?}: leaf at depth {}, expected {}",
+                path, depth, expected
+            ),
+            Violation::LengthMismatch {
+                path,
+                recorded,
+                actual,
+            } => write!(
+                f,
+                "{:?}: recorded length {} but holds {} entries",
+                path, recorded, actual
+            ),
+        }
+    }
+}
+
+/// State shared while walking the tree.
+pub struct Checker<K> {
+    pub path: Vec<usize>,
+    violations: Vec<Violation<K>>,
+    leaf_depth: Option<usize>,
+}
+
+impl<K> Checker<K> {
+    pub fn new() -> Self {
+        Checker {
+            path: Vec::new(),
+            violations: Vec::new(),
+            leaf_depth: None,
+        }
+    }
+
+    pub fn report(&mut self, violation: Violation<K>) {
+        self.violations.push(violation);
+    }
+
+    /// Records that a leaf was reached at `depth`; all leaves must share the
+    /// depth of the first one.
+    pub fn reach_leaf(&mut self, depth: usize) {
+        match self.leaf_depth {
+            Some(expected) if expected != depth => self.report(Violation::UnevenDepth {
+                path: self.path.clone(),
+                depth,
+                expected,
+            }),
+            Some(_) => {}
+            None => self.leaf_depth = Some(depth),
+        }
+    }
+
+    pub fn finish(self, entries: usize) -> CheckReport<K> {
+        CheckReport {
+            violations: self.violations,
+            entries,
+            height: self.leaf_depth.unwrap_or(0),
+        }
+    }
+}
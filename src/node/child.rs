@@ -0,0 +1,166 @@
+use super::*;
+
+/// A node below the root. Children are kept as an enum rather than boxed
+/// trait objects, so a descent costs a match instead of a vtable call. They
+/// are shared through `Arc`, so that a snapshot of the tree only copies the
+/// nodes a later write actually touches.
+#[derive(Debug, Clone)]
+pub enum Child<K, V, C = Natural> {
+    Leaf(LeafNode<K, V, C>),
+    Intermediate(IntermediateNode<K, V, C>),
+}
+
+impl<K, V, C> Child<K, V, C>
+where
+    K: Ord,
+    C: Comparator<K> + Clone,
+{
+    pub fn last_key(&self) -> Option<&K> {
+        match self {
+            Child::Leaf(node) => node.last_key(),
+            Child::Intermediate(node) => node.last_key(),
+        }
+    }
+
+    pub fn is_underflow(&self) -> bool {
+        match self {
+            Child::Leaf(node) => node.is_underflow(),
+            Child::Intermediate(node) => node.is_underflow(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Child::Leaf(node) => node.len(),
+            Child::Intermediate(node) => node.len(),
+        }
+    }
+
+    /// Number of entries of a leaf, or of children of an intermediate node.
+    pub fn width(&self) -> usize {
+        match self {
+            Child::Leaf(node) => node.len(),
+            Child::Intermediate(node) => node.width(),
+        }
+    }
+
+    pub fn rank(&self, key: &K, inclusive: bool) -> usize {
+        match self {
+            Child::Leaf(node) => node.rank(key, inclusive),
+            Child::Intermediate(node) => node.rank(key, inclusive),
+        }
+    }
+
+    /// Pairs the node with its last key, as stored in `IntermediateNode`.
+    /// Only nodes holding entries are keyed.
+    pub fn keyed(self) -> Keyed<K, V, C>
+    where
+        K: Clone,
+    {
+        let last_key = self.last_key().expect("keyed node is empty").clone();
+        (last_key, Arc::new(self))
+    }
+
+    pub fn nth(&self, idx: usize) -> Option<(&K, &V)> {
+        match self {
+            Child::Leaf(node) => node.nth(idx),
+            Child::Intermediate(node) => node.nth(idx),
+        }
+    }
+}
+
+impl<K, V, C> Child<K, V, C>
+where
+    K: 'static + fmt::Debug + Clone + Ord,
+    V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone,
+{
+    pub fn take(&mut self, key: &K) -> Result<(K, V), NodeError> {
+        match self {
+            Child::Leaf(node) => node.take(key),
+            Child::Intermediate(node) => node.take(key),
+        }
+    }
+
+    pub fn split_off(&mut self, key: &K, inclusive: bool) -> Self {
+        match self {
+            Child::Leaf(node) => Child::Leaf(node.split_off(key, inclusive)),
+            Child::Intermediate(node) => Child::Intermediate(node.split_off(key, inclusive)),
+        }
+    }
+
+    /// Merges two siblings into one node, or into two evenly filled nodes if
+    /// their entries do not fit into one.
+    pub fn merge(self, other: Self) -> Vec<Keyed<K, V, C>> {
+        let nodes = match (self, other) {
+            (Child::Leaf(mut first), Child::Leaf(second)) => {
+                first.append(second);
+                match first.split() {
+                    Some(second) => vec![Child::Leaf(first), Child::Leaf(second)],
+                    None => vec![Child::Leaf(first)],
+                }
+            }
+            (Child::Intermediate(mut first), Child::Intermediate(second)) => {
+                first.append(second);
+                match first.split() {
+                    Some(second) => vec![Child::Intermediate(first), Child::Intermediate(second)],
+                    None => vec![Child::Intermediate(first)],
+                }
+            }
+            _ => unreachable!("siblings are on the same level"),
+        };
+
+        nodes.into_iter().map(Child::keyed).collect()
+    }
+
+    pub fn insert(&mut self, key: &K, value: V) -> Result<Option<Overflow<K, V, C>>, NodeError> {
+        match self {
+            Child::Leaf(node) => node.insert(key, value),
+            Child::Intermediate(node) => node.insert(key, value),
+        }
+    }
+
+    pub fn update(&mut self, key: &K, value: V) -> Result<(), NodeError> {
+        match self {
+            Child::Leaf(node) => node.update(key, value),
+            Child::Intermediate(node) => node.update(key, value),
+        }
+    }
+}
+
+impl<K, V, C> sealed::Sealed for Child<K, V, C> {}
+
+impl<K, V, C> Node<K, V> for Child<K, V, C>
+where
+    K: 'static + fmt::Debug + Clone + Ord,
+    V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone,
+{
+    fn find(&self, key: &K) -> Option<&V> {
+        match self {
+            Child::Leaf(node) => node.find(key),
+            Child::Intermediate(node) => node.find(key),
+        }
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        match self {
+            Child::Leaf(node) => node.get_mut(key),
+            Child::Intermediate(node) => node.get_mut(key),
+        }
+    }
+
+    fn collect(&self) -> Vec<(K, V)> {
+        match self {
+            Child::Leaf(node) => node.collect(),
+            Child::Intermediate(node) => node.collect(),
+        }
+    }
+
+    fn range<'a>(&'a self, start: Bound<&K>, end: Bound<&K>) -> Range<'a, K, V> {
+        match self {
+            Child::Leaf(node) => node.range(start, end),
+            Child::Intermediate(node) => node.range(start, end),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use super::*;
+use std::cmp::Ordering;
+
+/// Order of the keys in a tree. A comparator is saved by name along with a
+/// dump, so comparators that order keys differently need different names.
+///
+/// Trees take their comparator as a type parameter, so comparing keys under
+/// `Natural` compiles down to `Ord::cmp`. `Collation` picks one at run time.
+pub trait Comparator<K>: Send + Sync {
+    fn name(&self) -> &str;
+    fn compare(&self, first: &K, second: &K) -> Ordering;
+
+    fn lt(&self, first: &K, second: &K) -> bool {
+        self.compare(first, second) == Ordering::Less
+    }
+
+    fn le(&self, first: &K, second: &K) -> bool {
+        self.compare(first, second) != Ordering::Greater
+    }
+}
+
+/// Orders keys by their `Ord` implementation.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Natural;
+
+impl<K> Comparator<K> for Natural
+where
+    K: Ord,
+{
+    fn name(&self) -> &str {
+        "natural"
+    }
+
+    fn compare(&self, first: &K, second: &K) -> Ordering {
+        first.cmp(second)
+    }
+}
+
+/// Orders string keys by their lowercase form, so keys differing only in
+/// case are the same key.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CaseInsensitive;
+
+impl<K> Comparator<K> for CaseInsensitive
+where
+    K: AsRef<str>,
+{
+    fn name(&self) -> &str {
+        "case-insensitive"
+    }
+
+    fn compare(&self, first: &K, second: &K) -> Ordering {
+        let first = first.as_ref().chars().flat_map(char::to_lowercase);
+        let second = second.as_ref().chars().flat_map(char::to_lowercase);
+        first.cmp(second)
+    }
+}
+
+/// A comparator chosen at run time, such as one named in a configuration.
+/// Every comparison goes through a vtable call, so trees whose order is known
+/// at compile time should take their comparator directly. Collations are
+/// told apart by the name of their comparator.
+pub struct Collation<K> {
+    comparator: Arc<dyn Comparator<K>>,
+}
+
+impl<K> Collation<K> {
+    pub fn new<C>(comparator: C) -> Self
+    where
+        C: 'static + Comparator<K>,
+    {
+        Collation {
+            comparator: Arc::new(comparator),
+        }
+    }
+
+    pub fn natural() -> Self
+    where
+        K: Ord,
+    {
+        Collation::new(Natural)
+    }
+}
+
+impl<K> Comparator<K> for Collation<K> {
+    fn name(&self) -> &str {
+        self.comparator.name()
+    }
+
+    fn compare(&self, first: &K, second: &K) -> Ordering {
+        self.comparator.compare(first, second)
+    }
+}
+
+impl<K> Clone for Collation<K> {
+    fn clone(&self) -> Self {
+        Collation {
+            comparator: Arc::clone(&self.comparator),
+        }
+    }
+}
+
+impl<K> Default for Collation<K>
+where
+    K: Ord,
+{
+    fn default() -> Self {
+        Collation::natural()
+    }
+}
+
+impl<K> PartialEq for Collation<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.name() == other.name()
+    }
+}
+
+impl<K> Eq for Collation<K> {}
+
+impl<K> fmt::Debug for Collation<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Collation").field(&self.name()).finish()
+    }
+}
@@ -0,0 +1,278 @@
+use super::*;
+use parking_lot::{ArcRwLockReadGuard, ArcRwLockWriteGuard, RawRwLock, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+type Latch<K, V> = Arc<RwLock<Latched<K, V>>>;
+type ReadGuard<K, V> = ArcRwLockReadGuard<RawRwLock, Latched<K, V>>;
+type WriteGuard<K, V> = ArcRwLockWriteGuard<RawRwLock, Latched<K, V>>;
+
+/// A node of `ConcurrentTree`. Child `idx` of an intermediate node holds the
+/// keys from `pivots[idx - 1]` up to but excluding `pivots[idx]`, so inserts
+/// that do not split never have to touch an ancestor.
+enum Latched<K, V> {
+    Leaf(Vec<(K, V)>),
+    Intermediate {
+        pivots: Vec<K>,
+        children: Vec<Latch<K, V>>,
+    },
+}
+
+/// B+tree that many threads can read and write at once. Every node sits
+/// behind its own reader/writer latch and operations couple latches on the
+/// way down, holding a node's latch until the child's is acquired.
+///
+/// Inserts first try to only write-latch the target leaf; if that leaf
+/// would split, they start over write-latching the whole path and release
+/// ancestors as soon as a node is reached that cannot split. Removal never
+/// merges nodes, so it needs the same latches as an insert that fits.
+pub struct ConcurrentTree<K, V> {
+    root: RwLock<Latch<K, V>>,
+    len: AtomicUsize,
+    fanout: Fanout,
+}
+
+impl<K, V> Latched<K, V>
+where
+    K: Clone + Ord,
+{
+    fn child(&self, key: &K) -> Option<&Latch<K, V>> {
+        match self {
+            Latched::Leaf(_) => None,
+            Latched::Intermediate { pivots, children } => {
+                Some(&children[pivots.partition_point(|pivot| pivot <= key)])
+            }
+        }
+    }
+
+    /// Whether one more entry or child would still fit without a split.
+    fn is_safe(&self, fanout: Fanout) -> bool {
+        match self {
+            Latched::Leaf(kv_series) => kv_series.len() < fanout.leaf,
+            Latched::Intermediate { children, .. } => children.len() < fanout.intermediate,
+        }
+    }
+
+    /// Moves the upper half out if this node is over capacity, returning
+    /// the first key of the new node along with it.
+    fn split(&mut self, fanout: Fanout) -> Option<(K, Latched<K, V>)> {
+        match self {
+            Latched::Leaf(kv_series) if kv_series.len() > fanout.leaf => {
+                let second = kv_series.split_off(kv_series.len().div_ceil(2));
+                Some((second[0].0.clone(), Latched::Leaf(second)))
+            }
+            Latched::Intermediate { pivots, children } if children.len() > fanout.intermediate => {
+                let at = children.len().div_ceil(2);
+                let second_children = children.split_off(at);
+                let second_pivots = pivots.split_off(at);
+                let pivot = pivots.pop().expect("an intermediate node has pivots");
+                Some((
+                    pivot,
+                    Latched::Intermediate {
+                        pivots: second_pivots,
+                        children: second_children,
+                    },
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<K, V> ConcurrentTree<K, V>
+where
+    K: fmt::Debug + Clone + Ord,
+    V: fmt::Debug + Clone,
+{
+    pub fn new() -> Self {
+        Self::with_fanout(Fanout::default())
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `fanout` is not valid.
+    pub fn with_fanout(fanout: Fanout) -> Self {
+        assert!(fanout.is_valid(), "fanout too small: {:?}", fanout);
+        ConcurrentTree {
+            root: RwLock::new(Arc::new(RwLock::new(Latched::Leaf(Vec::new())))),
+            len: AtomicUsize::new(0),
+            fanout,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Read-latches the path to the leaf that may hold `key`, releasing
+    /// each node once its child is latched.
+    fn read_leaf(&self, key: &K) -> ReadGuard<K, V> {
+        let root = self.root.read();
+        let mut guard = root.read_arc();
+        drop(root);
+        while let Some(child) = guard.child(key) {
+            guard = child.read_arc();
+        }
+        guard
+    }
+
+    pub fn find(&self, key: &K) -> Option<V> {
+        match &*self.read_leaf(key) {
+            Latched::Leaf(kv_series) => kv_series
+                .binary_search_by(|(stored_key, _)| stored_key.cmp(key))
+                .ok()
+                .map(|idx| kv_series[idx].1.clone()),
+            Latched::Intermediate { .. } => None,
+        }
+    }
+
+    /// Read-latches the path down to the parent of the leaf that may hold
+    /// `key` and write-latches only the leaf. Nothing above the leaf can
+    /// change while the parent stays latched, so the leaf may be released
+    /// and latched again for writing.
+    fn write_leaf(&self, key: &K) -> WriteGuard<K, V> {
+        let root = self.root.read();
+        let mut guard = root.read_arc();
+        if let Latched::Leaf(_) = &*guard {
+            drop(guard);
+            return root.write_arc();
+        }
+        drop(root);
+        loop {
+            let child = guard
+                .child(key)
+                .expect("only leaves have no children")
+                .clone();
+            let child_guard = child.read_arc();
+            if let Latched::Leaf(_) = &*child_guard {
+                drop(child_guard);
+                return child.write_arc();
+            }
+            guard = child_guard;
+        }
+    }
+
+    pub fn insert(&self, key: &K, value: V) -> Result<(), NodeError> {
+        if let Latched::Leaf(kv_series) = &mut *self.write_leaf(key) {
+            match kv_series.binary_search_by(|(stored_key, _)| stored_key.cmp(key)) {
+                Ok(_) => return Err(NodeError::Duplicated),
+                Err(idx) if kv_series.len() < self.fanout.leaf => {
+                    kv_series.insert(idx, (key.clone(), value));
+                    self.len.fetch_add(1, Ordering::AcqRel);
+                    return Ok(());
+                }
+                Err(_) => {}
+            }
+        }
+        self.insert_splitting(key, value)
+    }
+
+    /// Inserts with write latches on every node that may split, starting
+    /// from the root pointer. Latches above a node that has room for one
+    /// more entry are released as soon as it is reached.
+    fn insert_splitting(&self, key: &K, value: V) -> Result<(), NodeError> {
+        let mut root = Some(self.root.write());
+        let mut guard = root.as_ref().expect("the root is latched").write_arc();
+        let mut ancestors = Vec::new();
+        loop {
+            if guard.is_safe(self.fanout) {
+                root = None;
+                ancestors.clear();
+            }
+            let child = match guard.child(key) {
+                Some(child) => child.clone(),
+                None => break,
+            };
+            ancestors.push(std::mem::replace(&mut guard, child.write_arc()));
+        }
+
+        if let Latched::Leaf(kv_series) = &mut *guard {
+            match kv_series.binary_search_by(|(stored_key, _)| stored_key.cmp(key)) {
+                Ok(_) => return Err(NodeError::Duplicated),
+                Err(idx) => kv_series.insert(idx, (key.clone(), value)),
+            }
+        }
+        self.len.fetch_add(1, Ordering::AcqRel);
+
+        while let Some((pivot, second)) = guard.split(self.fanout) {
+            let second = Arc::new(RwLock::new(second));
+            match ancestors.pop() {
+                Some(mut parent) => {
+                    if let Latched::Intermediate { pivots, children } = &mut *parent {
+                        let idx = pivots.partition_point(|stored_pivot| stored_pivot <= &pivot);
+                        pivots.insert(idx, pivot);
+                        children.insert(idx + 1, second);
+                    }
+                    guard = parent;
+                }
+                None => {
+                    let root = root
+                        .as_mut()
+                        .expect("the root stays latched while it may split");
+                    let first = ArcRwLockWriteGuard::rwlock(&guard).clone();
+                    **root = Arc::new(RwLock::new(Latched::Intermediate {
+                        pivots: vec![pivot],
+                        children: vec![first, second],
+                    }));
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn update(&self, key: &K, value: V) -> Result<(), NodeError> {
+        if let Latched::Leaf(kv_series) = &mut *self.write_leaf(key) {
+            if let Ok(idx) = kv_series.binary_search_by(|(stored_key, _)| stored_key.cmp(key)) {
+                kv_series[idx].1 = value;
+                return Ok(());
+            }
+        }
+        Err(NodeError::NotFound)
+    }
+
+    /// Removes `key` from its leaf. Leaves are never merged, so a tree that
+    /// shrinks a lot keeps its shape until it is rebuilt.
+    pub fn remove(&self, key: &K) -> Result<(), NodeError> {
+        if let Latched::Leaf(kv_series) = &mut *self.write_leaf(key) {
+            if let Ok(idx) = kv_series.binary_search_by(|(stored_key, _)| stored_key.cmp(key)) {
+                kv_series.remove(idx);
+                self.len.fetch_sub(1, Ordering::AcqRel);
+                return Ok(());
+            }
+        }
+        Err(NodeError::NotFound)
+    }
+
+    /// Copies out every entry in key order, read-latching one subtree at a
+    /// time.
+    pub fn collect(&self) -> Vec<(K, V)> {
+        fn collect_into<K: Clone, V: Clone>(guard: ReadGuard<K, V>, kv_series: &mut Vec<(K, V)>) {
+            match &*guard {
+                Latched::Leaf(leaf) => kv_series.extend(leaf.iter().cloned()),
+                Latched::Intermediate { children, .. } => {
+                    for child in children {
+                        collect_into(child.read_arc(), kv_series);
+                    }
+                }
+            }
+        }
+
+        let mut kv_series = Vec::with_capacity(self.len());
+        collect_into(self.root.read().read_arc(), &mut kv_series);
+        kv_series
+    }
+}
+
+impl<K, V> Default for ConcurrentTree<K, V>
+where
+    K: fmt::Debug + Clone + Ord,
+    V: fmt::Debug + Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,138 @@
+use super::*;
+
+pub enum Entry<'a, K, V, C = Natural>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V, C>),
+}
+
+pub struct OccupiedEntry<'a, K, V> {
+    key: K,
+    value: &'a mut V,
+}
+
+pub struct VacantEntry<'a, K, V, C = Natural>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    key: K,
+    slot: Slot,
+    root_node: &'a mut RootNode<K, V, C>,
+}
+
+/// Where a key is or belongs in a tree, as found by a single descent.
+#[derive(Debug, Default)]
+pub(crate) struct Slot {
+    /// Position of the child taken at each level, then the position in the
+    /// leaf.
+    pub path: Vec<usize>,
+    pub occupied: bool,
+    /// Whether the leaf can take another entry without splitting.
+    pub has_room: bool,
+}
+
+impl<'a, K, V, C> Entry<'a, K, V, C>
+where
+    K: 'static + fmt::Debug + Clone + Ord,
+    V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone,
+{
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        match self {
+            Entry::Occupied(entry) => {
+                f(entry.value);
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+
+    pub fn or_insert(self, value: V) -> &'a mut V {
+        self.or_insert_with(|| value)
+    }
+
+    pub fn or_insert_with<F>(self, f: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(f()),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+}
+
+impl<'a, K, V> OccupiedEntry<'a, K, V> {
+    pub(super) fn new(key: K, value: &'a mut V) -> Self {
+        OccupiedEntry { key, value }
+    }
+
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn get(&self) -> &V {
+        self.value
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        self.value
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        self.value
+    }
+
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.value, value)
+    }
+}
+
+impl<'a, K, V, C> VacantEntry<'a, K, V, C>
+where
+    K: 'static + fmt::Debug + Clone + Ord,
+    V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone,
+{
+    pub(super) fn new(key: K, slot: Slot, root_node: &'a mut RootNode<K, V, C>) -> Self {
+        VacantEntry {
+            key,
+            slot,
+            root_node,
+        }
+    }
+
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Inserts the entry where the key was found missing.
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.root_node.insert_at(self.slot, &self.key, value)
+    }
+}
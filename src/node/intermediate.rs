@@ -1,25 +1,231 @@
 use super::*;
-use std::{cmp::max, fmt};
+use std::{fmt, ops::Bound};
 
-pub struct IntermediateNode<K, V, const N: usize> {
-    children: Vec<(K, Box<dyn Node<K, V, N>>)>,
+#[derive(Debug, Clone)]
+pub struct IntermediateNode<K, V, C = Natural> {
+    children: Vec<Keyed<K, V, C>>,
+    len: usize,
+    fanout: Fanout,
+    collation: C,
 }
 
-impl<K, V, const N: usize> IntermediateNode<K, V, N>
+impl<K, V, C> IntermediateNode<K, V, C>
 where
     K: Ord,
+    C: Comparator<K> + Clone,
 {
-    pub fn new(children: Vec<(K, Box<dyn Node<K, V, N>>)>) -> Self {
-        IntermediateNode { children }
+    pub fn new(children: Vec<Keyed<K, V, C>>, fanout: Fanout, collation: C) -> Self {
+        let len = children.iter().map(|(_, child)| child.len()).sum();
+        IntermediateNode {
+            children,
+            len,
+            fanout,
+            collation,
+        }
     }
 
-    fn get_child_index(&self, key: &K) -> usize {
+    /// Number of entries in the whole subtree.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Number of children.
+    pub fn width(&self) -> usize {
+        self.children.len()
+    }
+
+    pub fn last_key(&self) -> Option<&K> {
+        self.children.last().map(|(key, _)| key)
+    }
+
+    pub fn is_underflow(&self) -> bool {
+        self.children.len() < self.fanout.intermediate_minimum()
+    }
+
+    pub fn append(&mut self, mut other: Self) {
+        self.children.append(&mut other.children);
+        self.len += other.len;
+    }
+
+    pub fn fanout(&self) -> Fanout {
+        self.fanout
+    }
+
+    pub fn collation(&self) -> &C {
+        &self.collation
+    }
+
+    /// Number of levels below this node, which is zero only while it has no
+    /// children at all.
+    pub fn height(&self) -> usize {
+        match self.children.first() {
+            Some((_, child)) => match &**child {
+                Child::Leaf(_) => 1,
+                Child::Intermediate(node) => node.height() + 1,
+            },
+            None => 0,
+        }
+    }
+
+    pub fn into_children(self) -> Vec<Keyed<K, V, C>> {
         self.children
-            .binary_search_by_key(&key, |(key, _)| key)
-            .map_or_else(|idx| idx, |idx| idx)
     }
 
-    fn get_child(&self, key: &K) -> Option<&(K, Box<dyn Node<K, V, N>>)> {
+    /// Moves the upper half of the children into a new node if this one holds
+    /// more than the fanout allows.
+    pub fn split(&mut self) -> Option<Self> {
+        if self.children.len() > self.fanout.intermediate {
+            let second_node = IntermediateNode::new(
+                self.children.split_off(self.children.len().div_ceil(2)),
+                self.fanout,
+                self.collation.clone(),
+            );
+            self.len -= second_node.len;
+            Some(second_node)
+        } else {
+            None
+        }
+    }
+
+    /// Number of entries below `key`, or up to and including it if
+    /// `inclusive` is set.
+    pub fn rank(&self, key: &K, inclusive: bool) -> usize {
+        let idx = self.child_index(key, inclusive);
+        let below: usize = self.children[..idx]
+            .iter()
+            .map(|(_, child)| child.len())
+            .sum();
+        match self.children.get(idx) {
+            Some((_, child)) => below + child.rank(key, inclusive),
+            None => below,
+        }
+    }
+
+    pub fn nth(&self, mut idx: usize) -> Option<(&K, &V)> {
+        for (_, child) in self.children.iter() {
+            if idx < child.len() {
+                return child.nth(idx);
+            }
+            idx -= child.len();
+        }
+        None
+    }
+
+    /// Checks this subtree against its invariants, where every key has to be
+    /// greater than `lower`, and returns the number of entries.
+    pub fn check(
+        &self,
+        checker: &mut Checker<K>,
+        depth: usize,
+        lower: Option<&K>,
+        is_root: bool,
+    ) -> usize
+    where
+        K: Clone,
+    {
+        let len = self.children.len();
+        if len > self.fanout.intermediate {
+            checker.report(Violation::Overflow {
+                path: checker.path.clone(),
+                len,
+                capacity: self.fanout.intermediate,
+            });
+        }
+        if !is_root && self.is_underflow() {
+            checker.report(Violation::Underflow {
+                path: checker.path.clone(),
+                len,
+                minimum: self.fanout.intermediate_minimum(),
+            });
+        }
+        if !self
+            .children
+            .windows(2)
+            .all(|pair| self.collation.lt(&pair[0].0, &pair[1].0))
+        {
+            checker.report(Violation::UnsortedKeys {
+                path: checker.path.clone(),
+            });
+        }
+
+        let mut actual = 0;
+        let mut lower = lower;
+        for (idx, (separator, child)) in self.children.iter().enumerate() {
+            checker.path.push(idx);
+            actual += match &**child {
+                Child::Leaf(node) => node.check(checker, depth + 1, lower, is_root && len == 1),
+                Child::Intermediate(node) => node.check(checker, depth + 1, lower, false),
+            };
+            if child.last_key() != Some(separator) {
+                checker.report(Violation::SeparatorMismatch {
+                    path: checker.path.clone(),
+                    separator: separator.clone(),
+                    last_key: child.last_key().cloned(),
+                });
+            }
+            checker.path.pop();
+            lower = Some(separator);
+        }
+
+        if actual != self.len {
+            checker.report(Violation::LengthMismatch {
+                path: checker.path.clone(),
+                recorded: self.len,
+                actual,
+            });
+        }
+        actual
+    }
+
+    /// Prints each child below the separator key it is filed under.
+    pub fn fmt_tree(
+        &self,
+        f: &mut fmt::Formatter,
+        depth: usize,
+        limit: Option<usize>,
+    ) -> fmt::Result
+    where
+        K: fmt::Debug,
+        V: fmt::Debug,
+    {
+        for (separator, child) in self.children.iter() {
+            indent(f, depth)?;
+            writeln!(f, "<= {:?} ({})", separator, child.len())?;
+            match &**child {
+                Child::Leaf(node) => node.fmt_tree(f, depth + 1, limit)?,
+                Child::Intermediate(node) => node.fmt_tree(f, depth + 1, limit)?,
+            }
+        }
+        Ok(())
+    }
+
+    pub fn collect_stats(&self, stats: &mut TreeStats, depth: usize) {
+        for (_, child) in self.children.iter() {
+            stats.count_node(depth + 1);
+            match &**child {
+                Child::Leaf(node) => stats.entries += node.len(),
+                Child::Intermediate(node) => node.collect_stats(stats, depth + 1),
+            }
+        }
+    }
+
+    /// Index of the first child whose last key is not below `key`, or not
+    /// up to and including it if `inclusive` is set.
+    fn child_index(&self, key: &K, inclusive: bool) -> usize {
+        self.children.partition_point(|(last_key, _)| {
+            if inclusive {
+                self.collation.le(last_key, key)
+            } else {
+                self.collation.lt(last_key, key)
+            }
+        })
+    }
+
+    fn get_child_index(&self, key: &K) -> usize {
+        self.child_index(key, false)
+    }
+
+    fn get_child(&self, key: &K) -> Option<&Keyed<K, V, C>> {
         let idx = self.get_child_index(key);
         if idx == self.children.len() {
             self.children.last()
@@ -28,7 +234,7 @@ where
         }
     }
 
-    fn get_child_mut(&mut self, key: &K) -> Option<&mut (K, Box<dyn Node<K, V, N>>)> {
+    fn get_child_mut(&mut self, key: &K) -> Option<&mut Keyed<K, V, C>> {
         let idx = self.get_child_index(key);
         if idx == self.children.len() {
             self.children.last_mut()
@@ -38,76 +244,307 @@ where
     }
 }
 
-impl<K, V, const N: usize> Node<K, V, N> for IntermediateNode<K, V, N>
+impl<K, V, C> IntermediateNode<K, V, C>
 where
     K: 'static + fmt::Debug + Clone + Ord,
     V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone,
 {
-    fn find(&self, key: &K) -> Option<&V> {
-        self.get_child(&key).map_or(None, |child| child.1.find(key))
-    }
-
-    fn insert(&mut self, key: &K, value: V) -> Result<(), NodeError<K, V, N>> {
-        match self.get_child_mut(&key) {
-            Some(child) => {
-                let result = child.1.insert(&key, value);
-                child.0 = max(&child.0, &key).clone();
-
-                if let Err(NodeError::Overflow((first_last_key, second_last_key, second_node))) =
-                    result
-                {
-                    child.0 = first_last_key.clone();
-                    let idx = self.get_child_index(&first_last_key);
-                    self.children
-                        .insert(idx + 1, (second_last_key, second_node));
-                    if self.children.len() > N + 1 {
-                        let second_kv_series =
-                            self.children.split_off((self.children.len() + 1) / 2);
-                        let second_last_key =
-                            second_kv_series.last().ok_or(NodeError::Unknown)?.0.clone();
-                        let first_last_key =
-                            self.children.last().ok_or(NodeError::Unknown)?.0.clone();
-
-                        Err(NodeError::Overflow((
-                            first_last_key,
-                            second_last_key,
-                            Box::new(IntermediateNode {
-                                children: second_kv_series,
-                            }),
-                        )))
-                    } else {
-                        Ok(())
-                    }
-                } else {
-                    result
-                }
+    /// Merges the child at `idx` with a neighbour, or moves entries over from
+    /// it if both do not fit into a single node.
+    fn rebalance(&mut self, idx: usize) {
+        let idx = if idx == 0 { 0 } else { idx - 1 };
+        let (_, second) = self.children.remove(idx + 1);
+        let (_, first) = self.children.remove(idx);
+        let nodes = Arc::unwrap_or_clone(first).merge(Arc::unwrap_or_clone(second));
+        self.children.splice(idx..idx, nodes);
+    }
+
+    /// Inserts an entry below this node, splitting off its upper half if it
+    /// outgrows the fanout in turn.
+    pub fn insert(&mut self, key: &K, value: V) -> Result<Option<Overflow<K, V, C>>, NodeError> {
+        if self.children.is_empty() {
+            self.insert_first(key, value);
+            return Ok(None);
+        }
+
+        // Keys above every child go into the last one.
+        let idx = self.get_child_index(key).min(self.children.len() - 1);
+        let overflow = Arc::make_mut(&mut self.children[idx].1).insert(key, value)?;
+        Ok(self.inserted_into(idx, key, overflow))
+    }
+
+    /// Inserts an entry along `path`, the positions `locate` found for its
+    /// key, without comparing keys on the way down again. Afterwards `path`
+    /// leads to the new entry, with its first position counting the children
+    /// of this node and of the upper half split off it, if any, as one.
+    pub fn insert_at(
+        &mut self,
+        path: &mut [usize],
+        key: &K,
+        value: V,
+    ) -> Option<Overflow<K, V, C>> {
+        if self.children.is_empty() {
+            self.insert_first(key, value);
+            return None;
+        }
+        let (idx, rest) = path.split_first_mut().expect("path leads down to a leaf");
+        let child = Arc::make_mut(&mut self.children[*idx].1);
+        let overflow = match child {
+            Child::Leaf(node) => node.insert_at(rest[0], key, value),
+            Child::Intermediate(node) => node.insert_at(rest, key, value),
+        };
+        let child_idx = *idx;
+        if overflow.is_some() && rest[0] >= child.width() {
+            rest[0] -= child.width();
+            *idx += 1;
+        }
+        self.inserted_into(child_idx, key, overflow)
+    }
+
+    /// Inserts an entry along `path` like `insert_at`, where the leaf at its
+    /// end has room for it, so that no node splits and the value can be
+    /// borrowed where it landed.
+    pub fn place_at(&mut self, path: &[usize], key: &K, value: V) -> &mut V {
+        if self.children.is_empty() {
+            self.insert_first(key, value);
+            return self.value_at_mut(path);
+        }
+        let child = &mut self.children[path[0]];
+        let placed = match Arc::make_mut(&mut child.1) {
+            Child::Leaf(node) => node.place_at(path[1], key, value),
+            Child::Intermediate(node) => node.place_at(&path[1..], key, value),
+        };
+        if self.collation.lt(&child.0, key) {
+            child.0 = key.clone();
+        }
+        self.len += 1;
+        placed
+    }
+
+    fn insert_first(&mut self, key: &K, value: V) {
+        let leaf = LeafNode::new(
+            vec![(key.clone(), value)],
+            self.fanout,
+            self.collation.clone(),
+        );
+        self.children
+            .push((key.clone(), Arc::new(Child::Leaf(leaf))));
+        self.len = 1;
+    }
+
+    /// Accounts for an entry inserted into the child at `idx`, links in the
+    /// upper half the child split off if any, and splits this node in turn if
+    /// it outgrows the fanout.
+    fn inserted_into(
+        &mut self,
+        idx: usize,
+        key: &K,
+        overflow: Option<Overflow<K, V, C>>,
+    ) -> Option<Overflow<K, V, C>> {
+        // Indexing the field directly leaves `self.collation` free to be read
+        // while the child is borrowed.
+        let child = &mut self.children[idx];
+        if self.collation.lt(&child.0, key) {
+            child.0 = key.clone();
+        }
+        self.len += 1;
+
+        if let Some((first_last_key, second)) = overflow {
+            child.0 = first_last_key;
+            self.children.insert(idx + 1, second);
+        }
+        self.split().map(|second_node| {
+            let first_last_key = self.children[self.children.len() - 1].0.clone();
+            (first_last_key, Child::Intermediate(second_node).keyed())
+        })
+    }
+
+    /// Fills `slot` with the way down to where `key` is or would be
+    /// inserted. This is the only step of an entry that compares keys.
+    pub fn locate(&self, key: &K, slot: &mut Slot) {
+        if self.children.is_empty() {
+            slot.path.extend([0, 0]);
+            slot.has_room = true;
+            return;
+        }
+        let idx = self.get_child_index(key).min(self.children.len() - 1);
+        slot.path.push(idx);
+        match &*self.children[idx].1 {
+            Child::Leaf(node) => node.locate(key, slot),
+            Child::Intermediate(node) => node.locate(key, slot),
+        }
+    }
+
+    /// Value at the end of `path`, reached without comparing keys.
+    pub fn value_at_mut(&mut self, path: &[usize]) -> &mut V {
+        match Arc::make_mut(&mut self.children[path[0]].1) {
+            Child::Leaf(node) => node.value_at_mut(path[1]),
+            Child::Intermediate(node) => node.value_at_mut(&path[1..]),
+        }
+    }
+
+    pub fn update(&mut self, key: &K, value: V) -> Result<(), NodeError> {
+        let child = self.get_child_mut(key).ok_or(NodeError::NotFound)?;
+        Arc::make_mut(&mut child.1).update(key, value)
+    }
+
+    pub fn take(&mut self, key: &K) -> Result<(K, V), NodeError> {
+        let idx = self.get_child_index(key);
+        let child = self.children.get_mut(idx).ok_or(NodeError::NotFound)?;
+        let pair = Arc::make_mut(&mut child.1).take(key)?;
+
+        if let Some(last_key) = child.1.last_key() {
+            child.0 = last_key.clone();
+        }
+        let is_underflow = child.1.is_underflow();
+        self.len -= 1;
+        if is_underflow && self.children.len() > 1 {
+            self.rebalance(idx);
+        }
+        Ok(pair)
+    }
+
+    /// Moves the entries from `key` on, or those above it if `inclusive` is
+    /// set, into a new node. Only the children along the cut are split, and
+    /// pieces left empty are dropped, so both nodes may underflow along the
+    /// cut until `repair_edge` has run.
+    pub fn split_off(&mut self, key: &K, inclusive: bool) -> Self {
+        let idx = self.child_index(key, inclusive);
+        let mut second_children = self.children.split_off(idx);
+        if let Some((_, child)) = second_children.first_mut() {
+            let second = Arc::make_mut(child).split_off(key, inclusive);
+            let first = std::mem::replace(child, Arc::new(second));
+            if let Some(last_key) = first.last_key().cloned() {
+                self.children.push((last_key, first));
+            }
+            if child.len() == 0 {
+                second_children.remove(0);
             }
-            None => {
-                if self.children.len() == 0 {
-                    self.children = vec![(
-                        key.clone(),
-                        Box::new(LeafNode::new(vec![(key.clone(), value)])),
-                    )];
-                    Ok(())
-                } else {
-                    Err(NodeError::Unknown)
+        }
+
+        let second_node =
+            IntermediateNode::new(second_children, self.fanout, self.collation.clone());
+        self.len -= second_node.len;
+        second_node
+    }
+
+    /// Fixes underflowing nodes down the last edge of this root if `last` is
+    /// set, or down the first edge otherwise, as left behind by `split_off`.
+    pub fn repair_edge(&mut self, last: bool) {
+        self.collapse();
+        self.repair_edge_below(last);
+        self.collapse();
+    }
+
+    /// Repairs the subtree of the edge child first, then merges the child
+    /// with its neighbour if it still underflows. Every node off the edge is
+    /// whole, so a merge leaves nodes at least half full, but it also brings
+    /// an underflowing grandchild next to new siblings, so the new edge child
+    /// is repaired again. Each round removes a node, so this terminates.
+    fn repair_edge_below(&mut self, last: bool) {
+        loop {
+            let idx = if last {
+                self.children.len().saturating_sub(1)
+            } else {
+                0
+            };
+            if let Some((_, child)) = self.children.get_mut(idx) {
+                if let Child::Intermediate(node) = Arc::make_mut(child) {
+                    node.repair_edge_below(last);
                 }
             }
+            if self.children.len() > 1 && self.children[idx].1.is_underflow() {
+                self.rebalance(idx);
+            } else {
+                return;
+            }
+        }
+    }
+
+    /// Attaches `children` after the last child of the node `depth` levels
+    /// down the last edge if `at_end` is set, or before the first child down
+    /// the first edge otherwise. The two nodes meeting at the seam are
+    /// rebalanced if either underflows. Returns the upper half of this node
+    /// if it overflows in turn.
+    pub fn graft(
+        &mut self,
+        depth: usize,
+        mut children: Vec<Keyed<K, V, C>>,
+        at_end: bool,
+    ) -> Option<Self> {
+        self.len += children.iter().map(|(_, child)| child.len()).sum::<usize>();
+        if depth == 0 {
+            let seam = if at_end {
+                let seam = self.children.len();
+                self.children.append(&mut children);
+                seam
+            } else {
+                let seam = children.len();
+                children.append(&mut self.children);
+                self.children = children;
+                seam
+            };
+            if seam > 0
+                && seam < self.children.len()
+                && (self.children[seam - 1].1.is_underflow()
+                    || self.children[seam].1.is_underflow())
+            {
+                self.rebalance(seam);
+            }
+        } else {
+            let idx = if at_end { self.children.len() - 1 } else { 0 };
+            let child = &mut self.children[idx];
+            let second_node = match Arc::make_mut(&mut child.1) {
+                Child::Intermediate(node) => node.graft(depth - 1, children, at_end),
+                Child::Leaf(_) => unreachable!("graft depth exceeds the tree height"),
+            };
+            if let Some(last_key) = child.1.last_key() {
+                child.0 = last_key.clone();
+            }
+            if let Some(second_node) = second_node {
+                let keyed = Child::Intermediate(second_node).keyed();
+                self.children.insert(idx + 1, keyed);
+            }
+        }
+        self.split()
+    }
+
+    /// Replaces this node by its only child as long as that child is an
+    /// intermediate node, and drops the last leaf once it is empty.
+    pub fn collapse(&mut self) {
+        while self.children.len() == 1 {
+            match self.children.pop() {
+                Some((key, child)) => match Arc::unwrap_or_clone(child) {
+                    Child::Intermediate(node) => *self = node,
+                    Child::Leaf(node) => {
+                        if !node.is_empty() {
+                            self.children.push((key, Arc::new(Child::Leaf(node))));
+                        }
+                        break;
+                    }
+                },
+                None => break,
+            }
         }
     }
+}
 
-    fn update(&mut self, key: &K, value: V) -> Result<(), NodeError<K, V, N>> {
-        self.get_child_mut(&key)
-            .ok_or(NodeError::NotFound)?
-            .1
-            .update(key, value)
+impl<K, V, C> sealed::Sealed for IntermediateNode<K, V, C> {}
+
+impl<K, V, C> Node<K, V> for IntermediateNode<K, V, C>
+where
+    K: 'static + fmt::Debug + Clone + Ord,
+    V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone,
+{
+    fn find(&self, key: &K) -> Option<&V> {
+        self.get_child(key).and_then(|child| child.1.find(key))
     }
 
-    fn remove(&mut self, key: &K) -> Result<(), NodeError<K, V, N>> {
-        self.get_child_mut(&key)
-            .ok_or(NodeError::NotFound)?
-            .1
-            .remove(key)
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.get_child_mut(key)
+            .and_then(|child| Arc::make_mut(&mut child.1).get_mut(key))
     }
 
     fn collect(&self) -> Vec<(K, V)> {
@@ -116,4 +553,33 @@ where
             .flat_map(|(_, child)| child.collect())
             .collect()
     }
+
+    fn range<'a>(&'a self, start: Bound<&K>, end: Bound<&K>) -> Range<'a, K, V> {
+        // Each child is keyed by its greatest key, so only the children between
+        // the one holding `start` and the one holding `end` have to be visited.
+        let from = match start {
+            Bound::Included(start) => self.child_index(start, false),
+            Bound::Excluded(start) => self.child_index(start, true),
+            Bound::Unbounded => 0,
+        };
+        let to = match end {
+            Bound::Included(end) | Bound::Excluded(end) => {
+                (self.child_index(end, false) + 1).min(self.children.len())
+            }
+            Bound::Unbounded => self.children.len(),
+        };
+
+        if from >= to {
+            Box::new(std::iter::empty())
+        } else if from + 1 == to {
+            self.children[from].1.range(start, end)
+        } else {
+            let first = self.children[from].1.range(start, Bound::Unbounded);
+            let last = self.children[to - 1].1.range(Bound::Unbounded, end);
+            let middle = self.children[from + 1..to - 1]
+                .iter()
+                .flat_map(|(_, child)| child.range(Bound::Unbounded, Bound::Unbounded));
+            Box::new(first.chain(middle).chain(last))
+        }
+    }
 }
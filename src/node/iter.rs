@@ -0,0 +1,125 @@
+use super::*;
+use std::{collections::VecDeque, vec};
+
+/// Owning iterator over the entries of a `RootNode` in key order. Subtrees
+/// are opened only when iteration reaches them, and entries are moved out
+/// of every node the tree did not share with a snapshot or clone.
+pub struct IntoIter<K, V, C = Natural> {
+    front: vec::IntoIter<(K, V)>,
+    back: vec::IntoIter<(K, V)>,
+    pending: VecDeque<Arc<Child<K, V, C>>>,
+    len: usize,
+}
+
+impl<K, V, C> IntoIter<K, V, C>
+where
+    K: Clone + Ord,
+    V: Clone,
+    C: Comparator<K> + Clone,
+{
+    pub(super) fn new(root: IntermediateNode<K, V, C>) -> Self {
+        let len = root.len();
+        IntoIter {
+            front: Vec::new().into_iter(),
+            back: Vec::new().into_iter(),
+            pending: root
+                .into_children()
+                .into_iter()
+                .map(|(_, child)| child)
+                .collect(),
+            len,
+        }
+    }
+
+    /// Opens pending subtrees at the front until a leaf is reached, and
+    /// returns its entries.
+    fn open_front(&mut self) -> Option<vec::IntoIter<(K, V)>> {
+        while let Some(child) = self.pending.pop_front() {
+            match Arc::unwrap_or_clone(child) {
+                Child::Leaf(node) => return Some(node.into_kv_series().into_iter()),
+                Child::Intermediate(node) => {
+                    for (_, child) in node.into_children().into_iter().rev() {
+                        self.pending.push_front(child);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn open_back(&mut self) -> Option<vec::IntoIter<(K, V)>> {
+        while let Some(child) = self.pending.pop_back() {
+            match Arc::unwrap_or_clone(child) {
+                Child::Leaf(node) => return Some(node.into_kv_series().into_iter()),
+                Child::Intermediate(node) => {
+                    self.pending
+                        .extend(node.into_children().into_iter().map(|(_, child)| child));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<K, V, C> Iterator for IntoIter<K, V, C>
+where
+    K: Clone + Ord,
+    V: Clone,
+    C: Comparator<K> + Clone,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(pair) = self.front.next() {
+                self.len -= 1;
+                return Some(pair);
+            }
+            match self.open_front() {
+                Some(front) => self.front = front,
+                // Only the leaf already opened from the back is left.
+                None => {
+                    let pair = self.back.next()?;
+                    self.len -= 1;
+                    return Some(pair);
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K, V, C> DoubleEndedIterator for IntoIter<K, V, C>
+where
+    K: Clone + Ord,
+    V: Clone,
+    C: Comparator<K> + Clone,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(pair) = self.back.next_back() {
+                self.len -= 1;
+                return Some(pair);
+            }
+            match self.open_back() {
+                Some(back) => self.back = back,
+                None => {
+                    let pair = self.front.next_back()?;
+                    self.len -= 1;
+                    return Some(pair);
+                }
+            }
+        }
+    }
+}
+
+impl<K, V, C> ExactSizeIterator for IntoIter<K, V, C>
+where
+    K: Clone + Ord,
+    V: Clone,
+    C: Comparator<K> + Clone,
+{
+}
@@ -1,22 +1,239 @@
 use super::*;
+use std::ops::Bound;
 
-pub struct LeafNode<K, V, const N: usize> {
+#[derive(Debug, Clone)]
+pub struct LeafNode<K, V, C = Natural> {
     kv_series: Vec<(K, V)>,
+    fanout: Fanout,
+    collation: C,
 }
 
-impl<K, V, const N: usize> LeafNode<K, V, N> {
-    pub fn new(kv_series: Vec<(K, V)>) -> Self {
-        LeafNode { kv_series }
+impl<K, V, C> LeafNode<K, V, C>
+where
+    C: Comparator<K> + Clone,
+{
+    pub fn new(kv_series: Vec<(K, V)>, fanout: Fanout, collation: C) -> Self {
+        LeafNode {
+            kv_series,
+            fanout,
+            collation,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.kv_series.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.kv_series.is_empty()
+    }
+
+    pub fn into_kv_series(self) -> Vec<(K, V)> {
+        self.kv_series
+    }
+
+    pub fn last_key(&self) -> Option<&K> {
+        self.kv_series.last().map(|(key, _)| key)
+    }
+
+    pub fn is_underflow(&self) -> bool {
+        self.kv_series.len() < self.fanout.leaf_minimum()
+    }
+
+    /// Number of entries below `key`, or up to and including it if
+    /// `inclusive` is set.
+    pub fn rank(&self, key: &K, inclusive: bool) -> usize {
+        self.kv_series.partition_point(|(stored_key, _)| {
+            if inclusive {
+                self.collation.le(stored_key, key)
+            } else {
+                self.collation.lt(stored_key, key)
+            }
+        })
+    }
+
+    fn search(&self, key: &K) -> Result<usize, usize> {
+        self.kv_series
+            .binary_search_by(|(stored_key, _)| self.collation.compare(stored_key, key))
+    }
+
+    pub fn nth(&self, idx: usize) -> Option<(&K, &V)> {
+        self.kv_series.get(idx).map(|(key, value)| (key, value))
+    }
+
+    /// Inserts an entry, splitting off the upper half of the leaf if it
+    /// outgrows the fanout.
+    pub fn insert(&mut self, key: &K, new_value: V) -> Result<Option<Overflow<K, V, C>>, NodeError>
+    where
+        K: Clone + Ord,
+    {
+        match self.search(key) {
+            Ok(_) => Err(NodeError::Duplicated),
+            Err(idx) => Ok(self.insert_at(idx, key, new_value)),
+        }
+    }
+
+    /// Inserts an entry at `idx`, where `search` places its key, splitting
+    /// off the upper half of the leaf if it outgrows the fanout.
+    pub fn insert_at(&mut self, idx: usize, key: &K, new_value: V) -> Option<Overflow<K, V, C>>
+    where
+        K: Clone + Ord,
+    {
+        self.kv_series.insert(idx, (key.clone(), new_value));
+        self.split().map(|second_node| {
+            let first_last_key = self.kv_series[self.kv_series.len() - 1].0.clone();
+            (first_last_key, Child::Leaf(second_node).keyed())
+        })
+    }
+
+    /// Inserts an entry at `idx` into a leaf that has room for it, and
+    /// borrows its value.
+    pub fn place_at(&mut self, idx: usize, key: &K, new_value: V) -> &mut V
+    where
+        K: Clone,
+    {
+        self.kv_series.insert(idx, (key.clone(), new_value));
+        &mut self.kv_series[idx].1
+    }
+
+    /// Completes `slot` with the position of `key` in this leaf.
+    pub fn locate(&self, key: &K, slot: &mut Slot) {
+        let (idx, occupied) = match self.search(key) {
+            Ok(idx) => (idx, true),
+            Err(idx) => (idx, false),
+        };
+        slot.path.push(idx);
+        slot.occupied = occupied;
+        slot.has_room = self.kv_series.len() < self.fanout.leaf;
+    }
+
+    pub fn value_at_mut(&mut self, idx: usize) -> &mut V {
+        &mut self.kv_series[idx].1
+    }
+
+    pub fn update(&mut self, key: &K, new_value: V) -> Result<(), NodeError> {
+        let idx = self.search(key).map_err(|_| NodeError::NotFound)?;
+        self.kv_series[idx].1 = new_value;
+        Ok(())
+    }
+
+    pub fn take(&mut self, key: &K) -> Result<(K, V), NodeError> {
+        match self.search(key) {
+            Ok(idx) => Ok(self.kv_series.remove(idx)),
+            Err(_) => Err(NodeError::NotFound),
+        }
+    }
+
+    /// Checks this leaf against its invariants, where every key has to be
+    /// greater than `lower`, and returns the number of entries.
+    pub fn check(
+        &self,
+        checker: &mut Checker<K>,
+        depth: usize,
+        lower: Option<&K>,
+        is_sole: bool,
+    ) -> usize {
+        let len = self.kv_series.len();
+        if len > self.fanout.leaf {
+            checker.report(Violation::Overflow {
+                path: checker.path.clone(),
+                len,
+                capacity: self.fanout.leaf,
+            });
+        }
+        if self.is_underflow() && !(is_sole && len > 0) {
+            checker.report(Violation::Underflow {
+                path: checker.path.clone(),
+                len,
+                minimum: self.fanout.leaf_minimum(),
+            });
+        }
+        let is_sorted = self
+            .kv_series
+            .windows(2)
+            .all(|pair| self.collation.lt(&pair[0].0, &pair[1].0));
+        let is_above = match (lower, self.kv_series.first()) {
+            (Some(lower), Some((first_key, _))) => self.collation.lt(lower, first_key),
+            _ => true,
+        };
+        if !is_sorted || !is_above {
+            checker.report(Violation::UnsortedKeys {
+                path: checker.path.clone(),
+            });
+        }
+        checker.reach_leaf(depth);
+        len
+    }
+
+    /// Prints the entries on one line, at most `limit` of them if given.
+    pub fn fmt_tree(
+        &self,
+        f: &mut fmt::Formatter,
+        depth: usize,
+        limit: Option<usize>,
+    ) -> fmt::Result
+    where
+        K: fmt::Debug,
+        V: fmt::Debug,
+    {
+        indent(f, depth)?;
+        write!(f, "leaf ({}) {{", self.kv_series.len())?;
+        let shown = limit
+            .unwrap_or(self.kv_series.len())
+            .min(self.kv_series.len());
+        for (idx, (key, value)) in self.kv_series[..shown].iter().enumerate() {
+            if idx > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, " {:?}: {:?}", key, value)?;
+        }
+        if shown < self.kv_series.len() {
+            write!(f, ", … {} more", self.kv_series.len() - shown)?;
+        }
+        writeln!(f, " }}")
+    }
+
+    pub fn append(&mut self, mut other: Self) {
+        self.kv_series.append(&mut other.kv_series);
+    }
+
+    /// Moves the upper half of the entries into a new leaf if this one holds
+    /// more than the fanout allows.
+    pub fn split(&mut self) -> Option<Self> {
+        if self.kv_series.len() > self.fanout.leaf {
+            let second_kv_series = self.kv_series.split_off(self.kv_series.len().div_ceil(2));
+            Some(LeafNode::new(
+                second_kv_series,
+                self.fanout,
+                self.collation.clone(),
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Moves the entries from `key` on, or those above it if `inclusive` is
+    /// set, into a new leaf.
+    pub fn split_off(&mut self, key: &K, inclusive: bool) -> Self {
+        let idx = self.rank(key, inclusive);
+        LeafNode::new(
+            self.kv_series.split_off(idx),
+            self.fanout,
+            self.collation.clone(),
+        )
     }
 }
 
-impl<K, V, const N: usize> Node<K, V, N> for LeafNode<K, V, N>
+impl<K, V, C> sealed::Sealed for LeafNode<K, V, C> {}
+
+impl<K, V, C> Node<K, V> for LeafNode<K, V, C>
 where
     K: 'static + fmt::Debug + Clone + Ord,
     V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone,
 {
     fn find(&self, key: &K) -> Option<&V> {
-        match self.kv_series.binary_search_by_key(&key, |(key, _)| key) {
+        match self.search(key) {
             Ok(idx) => match self.kv_series.get(idx) {
                 Some((_, value)) => Some(value),
                 None => None,
@@ -25,56 +242,33 @@ where
         }
     }
 
-    fn insert(&mut self, key: &K, new_value: V) -> Result<(), NodeError<K, V, N>> {
-        let r = match self.kv_series.binary_search_by_key(&key, |(key, _)| key) {
-            Ok(_) => Err(NodeError::Duplicated),
-            Err(idx) => {
-                self.kv_series.insert(idx, (key.clone(), new_value));
-                Ok(())
-            }
-        };
-
-        if self.kv_series.len() > N {
-            let second_kv_series = self.kv_series.split_off((self.kv_series.len() + 1) / 2);
-            let second_last_key = second_kv_series.last().ok_or(NodeError::Unknown)?.0.clone();
-            let first_last_key = self.kv_series.last().ok_or(NodeError::Unknown)?.0.clone();
-
-            Err(NodeError::Overflow((
-                first_last_key,
-                second_last_key,
-                Box::new(LeafNode {
-                    kv_series: second_kv_series,
-                }),
-            )))
-        } else {
-            r
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        match self.search(key) {
+            Ok(idx) => self.kv_series.get_mut(idx).map(|(_, value)| value),
+            Err(_) => None,
         }
     }
 
-    fn update(&mut self, key: &K, new_value: V) -> Result<(), NodeError<K, V, N>> {
-        match self.kv_series.binary_search_by_key(&key, |(key, _)| key) {
-            Ok(idx) => match self.kv_series.get_mut(idx) {
-                Some((_, value)) => {
-                    *value = new_value;
-                    Ok(())
-                }
-                None => Err(NodeError::Unknown),
-            },
-            Err(_) => Err(NodeError::NotFound),
-        }
+    fn collect(&self) -> Vec<(K, V)> {
+        self.kv_series.clone()
     }
 
-    fn remove(&mut self, key: &K) -> Result<(), NodeError<K, V, N>> {
-        match self.kv_series.binary_search_by_key(&key, |(key, _)| key) {
-            Ok(idx) => {
-                self.kv_series.remove(idx);
-                Ok(())
-            }
-            Err(_) => Err(NodeError::NotFound),
-        }
-    }
+    fn range<'a>(&'a self, start: Bound<&K>, end: Bound<&K>) -> Range<'a, K, V> {
+        let from = match start {
+            Bound::Included(start) => self.rank(start, false),
+            Bound::Excluded(start) => self.rank(start, true),
+            Bound::Unbounded => 0,
+        };
+        let to = match end {
+            Bound::Included(end) => self.rank(end, true),
+            Bound::Excluded(end) => self.rank(end, false),
+            Bound::Unbounded => self.kv_series.len(),
+        };
 
-    fn collect(&self) -> Vec<(K, V)> {
-        self.kv_series.clone()
+        Box::new(
+            self.kv_series[from..to.max(from)]
+                .iter()
+                .map(|(key, value)| (key, value)),
+        )
     }
 }
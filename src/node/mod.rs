@@ -1,44 +1,161 @@
-use std::fmt;
+use serde::{Deserialize, Serialize};
+use std::{fmt, ops::Bound, sync::Arc};
 
+use check::Checker;
+pub use check::{CheckReport, Violation};
+pub use child::Child;
+pub use comparator::{CaseInsensitive, Collation, Comparator, Natural};
+pub use concurrent::ConcurrentTree;
+use entry::Slot;
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
 use intermediate::IntermediateNode;
+pub use iter::IntoIter;
 use leaf::LeafNode;
+pub use multimap::{MultiKey, MultiMap};
+pub use prefix::{ByteKey, PrefixRange, PrefixTree};
 pub use root::RootNode;
+pub use snapshot::Snapshot;
+pub use stats::TreeStats;
 
+mod check;
+mod child;
+mod comparator;
+mod concurrent;
+mod entry;
 mod intermediate;
+mod iter;
 mod leaf;
+mod multimap;
+mod prefix;
 mod root;
+mod snapshot;
+mod stats;
 
-#[derive(thiserror::Error, Debug)]
-pub enum NodeError<K, V, const N: usize>
-where
-    K: fmt::Debug,
-    V: fmt::Debug,
-{
-    #[error("node overflowed")]
-    Overflow((K, K, Box<dyn Node<K, V, N>>)),
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeError {
     #[error("key duplicated")]
     Duplicated,
     #[error("key not found")]
     NotFound,
-    #[error("unknown node error")]
-    Unknown,
+    #[error("keys not sorted")]
+    Unsorted,
+    #[error("fanout too small")]
+    InvalidFanout,
+}
+
+/// Node capacities of a tree, chosen when it is created and stored alongside
+/// its entries when it is dumped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fanout {
+    /// Most entries a leaf holds before it splits.
+    pub leaf: usize,
+    /// Most children an intermediate node holds before it splits.
+    pub intermediate: usize,
 }
 
-pub trait Node<K, V, const N: usize>
+impl Fanout {
+    pub fn new(leaf: usize, intermediate: usize) -> Self {
+        Fanout { leaf, intermediate }
+    }
+
+    /// Whether nodes can still be split in two and merged back.
+    pub fn is_valid(&self) -> bool {
+        self.leaf >= 2 && self.intermediate >= 3
+    }
+
+    fn leaf_minimum(&self) -> usize {
+        self.leaf.div_ceil(2)
+    }
+
+    fn intermediate_minimum(&self) -> usize {
+        self.intermediate.div_ceil(2)
+    }
+}
+
+impl Default for Fanout {
+    fn default() -> Self {
+        Fanout::new(32, 33)
+    }
+}
+
+/// A child together with its greatest key, as stored in `IntermediateNode`.
+type Keyed<K, V, C> = (K, Arc<Child<K, V, C>>);
+
+/// Upper half split off a node that outgrew its fanout during an insert,
+/// passed up for the parent to link in: the last key left in the lower half,
+/// and the new sibling.
+type Overflow<K, V, C> = (K, Keyed<K, V, C>);
+
+mod sealed {
+    /// Keeps `Node` implemented by the node types of this crate only.
+    pub trait Sealed {}
+}
+
+pub type Range<'a, K, V> = Box<dyn DoubleEndedIterator<Item = (&'a K, &'a V)> + 'a>;
+
+/// Lookups shared by the nodes of a tree. Writes that can change the shape
+/// of a tree are methods of `RootNode`, so a half-split node is never seen
+/// outside of it.
+pub trait Node<K, V>: sealed::Sealed
 where
     K: fmt::Debug,
     V: fmt::Debug,
 {
     fn find(&self, key: &K) -> Option<&V>;
-    fn insert(&mut self, key: &K, value: V) -> Result<(), NodeError<K, V, N>>;
-    fn update(&mut self, key: &K, value: V) -> Result<(), NodeError<K, V, N>>;
-    fn remove(&mut self, key: &K) -> Result<(), NodeError<K, V, N>>;
+    fn get_mut(&mut self, key: &K) -> Option<&mut V>;
     fn collect(&self) -> Vec<(K, V)>;
+    fn range<'a>(&'a self, start: Bound<&K>, end: Bound<&K>) -> Range<'a, K, V>;
+
+    fn first(&self) -> Option<(&K, &V)> {
+        self.range(Bound::Unbounded, Bound::Unbounded).next()
+    }
+
+    fn last(&self) -> Option<(&K, &V)> {
+        self.range(Bound::Unbounded, Bound::Unbounded).next_back()
+    }
+
+    /// Greatest entry whose key is less than or equal to `key`.
+    fn floor(&self, key: &K) -> Option<(&K, &V)> {
+        self.range(Bound::Unbounded, Bound::Included(key))
+            .next_back()
+    }
+
+    /// Smallest entry whose key is greater than or equal to `key`.
+    fn ceiling(&self, key: &K) -> Option<(&K, &V)> {
+        self.range(Bound::Included(key), Bound::Unbounded).next()
+    }
 }
 
-impl<K, V, const N: usize> fmt::Debug for dyn Node<K, V, N> {
+/// Splits `items` into as few chunks of at most `capacity` items as possible,
+/// with sizes differing by at most one.
+fn pack<T>(mut items: Vec<T>, capacity: usize) -> Vec<Vec<T>> {
+    let count = items.len().div_ceil(capacity).max(1);
+    let (size, extra) = (items.len() / count, items.len() % count);
+    let mut chunks = Vec::with_capacity(count);
+    for idx in (0..count).rev() {
+        let at = items.len() - size - if idx < extra { 1 } else { 0 };
+        chunks.push(items.split_off(at));
+    }
+    chunks.reverse();
+    chunks
+}
+
+impl<K, V> fmt::Debug for dyn Node<K, V>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "node")?;
-        Ok(())
+        f.debug_map()
+            .entries(self.range(Bound::Unbounded, Bound::Unbounded))
+            .finish()
+    }
+}
+
+/// Writes `depth` levels of indentation for the structural tree printer.
+fn indent(f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+    for _ in 0..depth {
+        write!(f, "  ")?;
     }
+    Ok(())
 }
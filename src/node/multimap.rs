@@ -0,0 +1,230 @@
+use super::*;
+use std::{
+    cmp::Ordering,
+    ops::{Bound, RangeBounds},
+};
+
+/// Key under which a `MultiMap` files an entry in its tree: the key itself
+/// with the value as a tiebreaker. `Before` and `After` are never stored;
+/// they are probes sorting just around all entries with their key.
+#[derive(Debug, Clone)]
+pub enum MultiKey<K, V> {
+    Before(K),
+    Entry(K, V),
+    After(K),
+}
+
+impl<K, V> MultiKey<K, V> {
+    fn key(&self) -> &K {
+        match self {
+            MultiKey::Before(key) | MultiKey::Entry(key, _) | MultiKey::After(key) => key,
+        }
+    }
+
+    fn position(&self) -> u8 {
+        match self {
+            MultiKey::Before(_) => 0,
+            MultiKey::Entry(_, _) => 1,
+            MultiKey::After(_) => 2,
+        }
+    }
+
+    /// Orders by key under `compare`, then by value between entries.
+    fn compare_by<F>(&self, other: &Self, compare: F) -> Ordering
+    where
+        F: FnOnce(&K, &K) -> Ordering,
+        V: Ord,
+    {
+        compare(self.key(), other.key()).then_with(|| match (self, other) {
+            (MultiKey::Entry(_, value), MultiKey::Entry(_, other_value)) => value.cmp(other_value),
+            _ => self.position().cmp(&other.position()),
+        })
+    }
+}
+
+impl<K, V> Ord for MultiKey<K, V>
+where
+    K: Ord,
+    V: Ord,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.compare_by(other, K::cmp)
+    }
+}
+
+impl<K, V> PartialOrd for MultiKey<K, V>
+where
+    K: Ord,
+    V: Ord,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K, V> PartialEq for MultiKey<K, V>
+where
+    K: Ord,
+    V: Ord,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K, V> Eq for MultiKey<K, V>
+where
+    K: Ord,
+    V: Ord,
+{
+}
+
+/// Orders the keys of a `MultiMap` by a collation of their own keys.
+#[derive(Clone)]
+struct MultiKeyOrder<C> {
+    keys: C,
+}
+
+impl<K, V, C> Comparator<MultiKey<K, V>> for MultiKeyOrder<C>
+where
+    V: Ord,
+    C: Comparator<K>,
+{
+    fn name(&self) -> &str {
+        self.keys.name()
+    }
+
+    fn compare(&self, first: &MultiKey<K, V>, second: &MultiKey<K, V>) -> Ordering {
+        first.compare_by(second, |first, second| self.keys.compare(first, second))
+    }
+}
+
+/// Tree holding any number of values per key, kept in value order. A key
+/// and value pair is stored at most once.
+pub struct MultiMap<K, V, C = Natural>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    tree: RootNode<MultiKey<K, V>, (), MultiKeyOrder<C>>,
+}
+
+impl<K, V> MultiMap<K, V>
+where
+    K: 'static + fmt::Debug + Clone + Ord,
+    V: 'static + fmt::Debug + Clone + Ord,
+{
+    pub fn new() -> Self {
+        Self::with_fanout(Fanout::default())
+    }
+
+    pub fn with_fanout(fanout: Fanout) -> Self {
+        Self::with_collation(fanout, Natural)
+    }
+}
+
+impl<K, V, C> MultiMap<K, V, C>
+where
+    K: 'static + fmt::Debug + Clone + Ord,
+    V: 'static + fmt::Debug + Clone + Ord,
+    C: Comparator<K> + Clone,
+{
+    /// Creates a map ordering its keys by `collation`. Values under a key are
+    /// still kept in their own order.
+    pub fn with_collation(fanout: Fanout, collation: C) -> Self {
+        MultiMap {
+            tree: RootNode::with_collation(fanout, MultiKeyOrder { keys: collation }),
+        }
+    }
+
+    pub fn fanout(&self) -> Fanout {
+        self.tree.fanout()
+    }
+
+    /// Number of key and value pairs.
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Result<(), NodeError> {
+        self.tree.insert(&MultiKey::Entry(key, value), ())
+    }
+
+    pub fn contains(&self, key: &K, value: &V) -> bool {
+        self.tree
+            .find(&MultiKey::Entry(key.clone(), value.clone()))
+            .is_some()
+    }
+
+    /// Values stored under `key`, in ascending order.
+    pub fn find_all(&self, key: &K) -> impl DoubleEndedIterator<Item = &V> + '_ {
+        self.range((Bound::Included(key), Bound::Included(key)))
+            .map(|(_, value)| value)
+    }
+
+    /// Number of values stored under `key`, without visiting them.
+    pub fn count(&self, key: &K) -> usize {
+        self.tree.count_range((
+            Bound::Excluded(MultiKey::Before(key.clone())),
+            Bound::Excluded(MultiKey::After(key.clone())),
+        ))
+    }
+
+    /// Removes a single pair, leaving other values under `key` in place.
+    pub fn remove_one(&mut self, key: &K, value: &V) -> Result<(), NodeError> {
+        self.tree
+            .remove(&MultiKey::Entry(key.clone(), value.clone()))
+    }
+
+    /// Pairs whose key is within `range`, ordered by key and then value.
+    pub fn range<R>(&self, range: R) -> Range<'_, K, V>
+    where
+        R: RangeBounds<K>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(key) => Bound::Excluded(MultiKey::Before(key.clone())),
+            Bound::Excluded(key) => Bound::Excluded(MultiKey::After(key.clone())),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(key) => Bound::Excluded(MultiKey::After(key.clone())),
+            Bound::Excluded(key) => Bound::Excluded(MultiKey::Before(key.clone())),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        Box::new(
+            self.tree
+                .range((start, end))
+                .map(|(multi_key, _)| match multi_key {
+                    MultiKey::Entry(key, value) => (key, value),
+                    _ => unreachable!("probes are never stored"),
+                }),
+        )
+    }
+}
+
+impl<K, V, C> Clone for MultiMap<K, V, C>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    /// Clones share all nodes with the original until either is written to.
+    fn clone(&self) -> Self {
+        MultiMap {
+            tree: self.tree.clone(),
+        }
+    }
+}
+
+impl<K, V> Default for MultiMap<K, V>
+where
+    K: 'static + fmt::Debug + Clone + Ord,
+    V: 'static + fmt::Debug + Clone + Ord,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
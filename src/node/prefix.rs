@@ -0,0 +1,572 @@
+use super::*;
+use std::{
+    cmp::Ordering,
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
+
+/// Keys that are compared and stored as their bytes. Their `Ord` has to
+/// agree with the order of their bytes, as it does for `String` and
+/// `Vec<u8>`.
+pub trait ByteKey: Clone + Ord {
+    fn as_bytes(&self) -> &[u8];
+    fn from_bytes(bytes: Vec<u8>) -> Self;
+}
+
+impl ByteKey for Vec<u8> {
+    fn as_bytes(&self) -> &[u8] {
+        self
+    }
+
+    fn from_bytes(bytes: Vec<u8>) -> Self {
+        bytes
+    }
+}
+
+impl ByteKey for String {
+    fn as_bytes(&self) -> &[u8] {
+        str::as_bytes(self)
+    }
+
+    fn from_bytes(bytes: Vec<u8>) -> Self {
+        String::from_utf8(bytes).expect("keys are only rebuilt from whole stored keys")
+    }
+}
+
+pub type PrefixRange<'a, K, V> = Box<dyn DoubleEndedIterator<Item = (K, &'a V)> + 'a>;
+
+type ByteRange<'a, V> = Box<dyn DoubleEndedIterator<Item = (Vec<u8>, &'a V)> + 'a>;
+
+fn common_prefix_len(first: &[u8], second: &[u8]) -> usize {
+    first
+        .iter()
+        .zip(second)
+        .take_while(|(first, second)| first == second)
+        .count()
+}
+
+/// Compares `prefix` followed by `suffix` with `key`, without joining them.
+fn compare(prefix: &[u8], suffix: &[u8], key: &[u8]) -> Ordering {
+    let split = prefix.len().min(key.len());
+    match prefix.cmp(&key[..split]) {
+        Ordering::Equal => suffix.cmp(&key[split..]),
+        ordering => ordering,
+    }
+}
+
+/// Shortest prefix of `first` that is still greater than `last`, which
+/// separates two neighbouring nodes as well as `first` itself would.
+fn truncate_separator(last: &[u8], first: &[u8]) -> Vec<u8> {
+    first[..common_prefix_len(last, first) + 1].to_vec()
+}
+
+/// Entries of a leaf, each key stored without the prefix all of them share.
+#[derive(Debug, Clone)]
+struct PrefixLeaf<V> {
+    prefix: Vec<u8>,
+    suffixes: Vec<Box<[u8]>>,
+    values: Vec<V>,
+}
+
+/// Children of an intermediate node. Child `idx` holds the keys from
+/// separator `idx - 1` up to but excluding separator `idx`, and separators
+/// are stored without the prefix all of them share.
+#[derive(Debug, Clone)]
+struct PrefixBranch<V> {
+    prefix: Vec<u8>,
+    separators: Vec<Box<[u8]>>,
+    children: Vec<PrefixNode<V>>,
+    len: usize,
+}
+
+#[derive(Debug, Clone)]
+enum PrefixNode<V> {
+    Leaf(PrefixLeaf<V>),
+    Branch(PrefixBranch<V>),
+}
+
+/// Splits `keys`, sorted in ascending order, into their common prefix and
+/// what is left of each. The common prefix of sorted keys is the one of
+/// the first and the last.
+fn compress(keys: Vec<Vec<u8>>) -> (Vec<u8>, Vec<Box<[u8]>>) {
+    let prefix = match (keys.first(), keys.last()) {
+        (Some(first), Some(last)) => first[..common_prefix_len(first, last)].to_vec(),
+        _ => Vec::new(),
+    };
+    let suffixes = keys
+        .into_iter()
+        .map(|key| key[prefix.len()..].into())
+        .collect();
+    (prefix, suffixes)
+}
+
+/// Moves the bytes of `prefix` past `len` back to the front of every suffix.
+fn shorten(prefix: &mut Vec<u8>, suffixes: &mut [Box<[u8]>], len: usize) {
+    if len < prefix.len() {
+        for suffix in suffixes.iter_mut() {
+            *suffix = [&prefix[len..], &suffix[..]].concat().into();
+        }
+        prefix.truncate(len);
+    }
+}
+
+/// Moves the bytes that every suffix starts with into `prefix`. As with
+/// `compress`, these are the ones the first and the last suffix share.
+fn lengthen(prefix: &mut Vec<u8>, suffixes: &mut [Box<[u8]>]) {
+    let len = match (suffixes.first(), suffixes.last()) {
+        (Some(first), Some(last)) => common_prefix_len(first, last),
+        _ => return,
+    };
+    if len > 0 {
+        prefix.extend_from_slice(&suffixes[0][..len]);
+        for suffix in suffixes.iter_mut() {
+            *suffix = suffix[len..].into();
+        }
+    }
+}
+
+/// Inserts `key` at `idx` of the sorted keys stored as `suffixes` of
+/// `prefix`. Only a key outside the prefix rewrites the other suffixes.
+fn insert_compressed(prefix: &mut Vec<u8>, suffixes: &mut Vec<Box<[u8]>>, idx: usize, key: &[u8]) {
+    if suffixes.is_empty() {
+        prefix.clear();
+        prefix.extend_from_slice(key);
+    } else {
+        shorten(prefix, suffixes, common_prefix_len(prefix, key));
+    }
+    suffixes.insert(idx, key[prefix.len()..].into());
+}
+
+/// Appends the keys stored as `other` suffixes of `other_prefix`, all of
+/// them greater than the ones stored as `suffixes` of `prefix`.
+fn append_compressed(
+    prefix: &mut Vec<u8>,
+    suffixes: &mut Vec<Box<[u8]>>,
+    other_prefix: &[u8],
+    other: Vec<Box<[u8]>>,
+) {
+    let len = common_prefix_len(prefix, other_prefix);
+    shorten(prefix, suffixes, len);
+    if len == other_prefix.len() {
+        suffixes.extend(other);
+    } else {
+        suffixes.extend(
+            other
+                .iter()
+                .map(|suffix| [&other_prefix[len..], &suffix[..]].concat().into()),
+        );
+    }
+}
+
+impl<V> PrefixLeaf<V> {
+    fn new(kv_series: Vec<(Vec<u8>, V)>) -> Self {
+        let (keys, values) = kv_series.into_iter().unzip();
+        let (prefix, suffixes) = compress(keys);
+        PrefixLeaf {
+            prefix,
+            suffixes,
+            values,
+        }
+    }
+
+    fn key(&self, idx: usize) -> Vec<u8> {
+        [&self.prefix[..], &self.suffixes[idx]].concat()
+    }
+
+    fn search(&self, key: &[u8]) -> Result<usize, usize> {
+        self.suffixes
+            .binary_search_by(|suffix| compare(&self.prefix, suffix, key))
+    }
+
+    /// Inserts a key that is not stored yet at `idx`. A key outside the
+    /// shared prefix shortens it, which rewrites every suffix.
+    fn insert_at(&mut self, idx: usize, key: &[u8], value: V) {
+        insert_compressed(&mut self.prefix, &mut self.suffixes, idx, key);
+        self.values.insert(idx, value);
+    }
+
+    /// Moves the entries from `at` on into a new leaf, returned along with
+    /// the separator in front of it. Both halves keep the longest prefix
+    /// their keys share.
+    fn split_off(&mut self, at: usize) -> (Vec<u8>, Self) {
+        let separator = truncate_separator(&self.key(at - 1), &self.key(at));
+        let mut second = PrefixLeaf {
+            prefix: self.prefix.clone(),
+            suffixes: self.suffixes.split_off(at),
+            values: self.values.split_off(at),
+        };
+        lengthen(&mut self.prefix, &mut self.suffixes);
+        lengthen(&mut second.prefix, &mut second.suffixes);
+        (separator, second)
+    }
+
+    /// Moves the entries of `other`, the next leaf, to the end of this one.
+    fn append(&mut self, other: Self) {
+        append_compressed(
+            &mut self.prefix,
+            &mut self.suffixes,
+            &other.prefix,
+            other.suffixes,
+        );
+        self.values.extend(other.values);
+    }
+
+    fn range<'a>(&'a self, start: Bound<&[u8]>, end: Bound<&[u8]>) -> ByteRange<'a, V> {
+        let from = match start {
+            Bound::Included(start) => self
+                .suffixes
+                .partition_point(|suffix| compare(&self.prefix, suffix, start) == Ordering::Less),
+            Bound::Excluded(start) => self.suffixes.partition_point(|suffix| {
+                compare(&self.prefix, suffix, start) != Ordering::Greater
+            }),
+            Bound::Unbounded => 0,
+        };
+        let to = match end {
+            Bound::Included(end) => self
+                .suffixes
+                .partition_point(|suffix| compare(&self.prefix, suffix, end) != Ordering::Greater),
+            Bound::Excluded(end) => self
+                .suffixes
+                .partition_point(|suffix| compare(&self.prefix, suffix, end) == Ordering::Less),
+            Bound::Unbounded => self.suffixes.len(),
+        };
+        Box::new((from..to.max(from)).map(move |idx| (self.key(idx), &self.values[idx])))
+    }
+}
+
+impl<V> PrefixBranch<V> {
+    fn new(separators: Vec<Vec<u8>>, children: Vec<PrefixNode<V>>) -> Self {
+        let (prefix, separators) = compress(separators);
+        let len = children.iter().map(PrefixNode::len).sum();
+        PrefixBranch {
+            prefix,
+            separators,
+            children,
+            len,
+        }
+    }
+
+    fn separator(&self, idx: usize) -> Vec<u8> {
+        [&self.prefix[..], &self.separators[idx]].concat()
+    }
+
+    /// Puts `child` after child `idx`, with `separator` in front of it. The
+    /// entries of `child` are already counted.
+    fn insert_child(&mut self, idx: usize, separator: &[u8], child: PrefixNode<V>) {
+        insert_compressed(&mut self.prefix, &mut self.separators, idx, separator);
+        self.children.insert(idx + 1, child);
+    }
+
+    /// Takes out the child after child `idx` along with the separator in
+    /// front of it. Its entries are still counted.
+    fn remove_child(&mut self, idx: usize) -> (Vec<u8>, PrefixNode<V>) {
+        let separator = self.separator(idx);
+        self.separators.remove(idx);
+        (separator, self.children.remove(idx + 1))
+    }
+
+    /// Moves the children from `at` on into a new branch, returned along
+    /// with the separator in front of it.
+    fn split_off(&mut self, at: usize) -> (Vec<u8>, Self) {
+        let children = self.children.split_off(at);
+        let len = children.iter().map(PrefixNode::len).sum();
+        let mut second = PrefixBranch {
+            prefix: self.prefix.clone(),
+            separators: self.separators.split_off(at),
+            children,
+            len,
+        };
+        let separator = self.separator(at - 1);
+        self.separators.pop();
+        self.len -= len;
+        lengthen(&mut self.prefix, &mut self.separators);
+        lengthen(&mut second.prefix, &mut second.separators);
+        (separator, second)
+    }
+
+    /// Moves the children of `other`, the next branch, to the end of this
+    /// one, with `separator` in between.
+    fn append(&mut self, separator: &[u8], other: Self) {
+        let idx = self.separators.len();
+        insert_compressed(&mut self.prefix, &mut self.separators, idx, separator);
+        append_compressed(
+            &mut self.prefix,
+            &mut self.separators,
+            &other.prefix,
+            other.separators,
+        );
+        self.children.extend(other.children);
+        self.len += other.len;
+    }
+
+    /// Index of the child that holds `key` if it is stored at all.
+    fn route(&self, key: &[u8]) -> usize {
+        self.separators
+            .partition_point(|separator| compare(&self.prefix, separator, key) != Ordering::Greater)
+    }
+
+    fn range<'a>(&'a self, start: Bound<&[u8]>, end: Bound<&[u8]>) -> ByteRange<'a, V> {
+        let from = match start {
+            Bound::Included(start) | Bound::Excluded(start) => self.route(start),
+            Bound::Unbounded => 0,
+        };
+        let to = match end {
+            Bound::Included(end) | Bound::Excluded(end) => self.route(end) + 1,
+            Bound::Unbounded => self.children.len(),
+        };
+
+        if from >= to {
+            Box::new(std::iter::empty())
+        } else if from + 1 == to {
+            self.children[from].range(start, end)
+        } else {
+            let first = self.children[from].range(start, Bound::Unbounded);
+            let last = self.children[to - 1].range(Bound::Unbounded, end);
+            let middle = self.children[from + 1..to - 1]
+                .iter()
+                .flat_map(|child| child.range(Bound::Unbounded, Bound::Unbounded));
+            Box::new(first.chain(middle).chain(last))
+        }
+    }
+}
+
+impl<V> PrefixNode<V> {
+    fn len(&self) -> usize {
+        match self {
+            PrefixNode::Leaf(node) => node.values.len(),
+            PrefixNode::Branch(node) => node.len,
+        }
+    }
+
+    fn is_underflow(&self, fanout: Fanout) -> bool {
+        match self {
+            PrefixNode::Leaf(node) => node.values.len() < fanout.leaf_minimum(),
+            PrefixNode::Branch(node) => node.children.len() < fanout.intermediate_minimum(),
+        }
+    }
+
+    fn find(&self, key: &[u8]) -> Option<&V> {
+        match self {
+            PrefixNode::Leaf(node) => node.search(key).ok().map(|idx| &node.values[idx]),
+            PrefixNode::Branch(node) => node.children[node.route(key)].find(key),
+        }
+    }
+
+    fn get_mut(&mut self, key: &[u8]) -> Option<&mut V> {
+        match self {
+            PrefixNode::Leaf(node) => match node.search(key) {
+                Ok(idx) => Some(&mut node.values[idx]),
+                Err(_) => None,
+            },
+            PrefixNode::Branch(node) => {
+                let idx = node.route(key);
+                node.children[idx].get_mut(key)
+            }
+        }
+    }
+
+    /// Inserts a new entry, or returns `None` if the key is already stored.
+    /// If this node overflows, its upper half is moved into a new node that
+    /// is returned along with the separator in front of it.
+    fn insert(&mut self, key: &[u8], value: V, fanout: Fanout) -> Option<Option<(Vec<u8>, Self)>> {
+        match self {
+            PrefixNode::Leaf(node) => {
+                let idx = node.search(key).err()?;
+                node.insert_at(idx, key, value);
+            }
+            PrefixNode::Branch(node) => {
+                let idx = node.route(key);
+                let split = node.children[idx].insert(key, value, fanout)?;
+                node.len += 1;
+                if let Some((separator, second)) = split {
+                    node.insert_child(idx, &separator, second);
+                }
+            }
+        }
+        Some(self.split(fanout))
+    }
+
+    fn split(&mut self, fanout: Fanout) -> Option<(Vec<u8>, Self)> {
+        match self {
+            PrefixNode::Leaf(node) if node.values.len() > fanout.leaf => {
+                let (separator, second) = node.split_off(node.values.len().div_ceil(2));
+                Some((separator, PrefixNode::Leaf(second)))
+            }
+            PrefixNode::Branch(node) if node.children.len() > fanout.intermediate => {
+                let (separator, second) = node.split_off(node.children.len().div_ceil(2));
+                Some((separator, PrefixNode::Branch(second)))
+            }
+            _ => None,
+        }
+    }
+
+    fn remove(&mut self, key: &[u8], fanout: Fanout) -> Option<V> {
+        match self {
+            PrefixNode::Leaf(node) => {
+                let idx = node.search(key).ok()?;
+                node.suffixes.remove(idx);
+                Some(node.values.remove(idx))
+            }
+            PrefixNode::Branch(node) => {
+                let idx = node.route(key);
+                let value = node.children[idx].remove(key, fanout)?;
+                node.len -= 1;
+                if node.children[idx].is_underflow(fanout) && node.children.len() > 1 {
+                    node.rebalance(idx, fanout);
+                }
+                Some(value)
+            }
+        }
+    }
+
+    fn range<'a>(&'a self, start: Bound<&[u8]>, end: Bound<&[u8]>) -> ByteRange<'a, V> {
+        match self {
+            PrefixNode::Leaf(node) => node.range(start, end),
+            PrefixNode::Branch(node) => node.range(start, end),
+        }
+    }
+}
+
+impl<V> PrefixBranch<V> {
+    /// Merges the child at `idx` with a neighbour, or moves entries over from
+    /// it if both do not fit into a single node.
+    fn rebalance(&mut self, idx: usize, fanout: Fanout) {
+        let idx = if idx == 0 { 0 } else { idx - 1 };
+        let (separator, second) = self.remove_child(idx);
+        match (&mut self.children[idx], second) {
+            (PrefixNode::Leaf(first), PrefixNode::Leaf(second)) => first.append(second),
+            (PrefixNode::Branch(first), PrefixNode::Branch(second)) => {
+                first.append(&separator, second)
+            }
+            _ => unreachable!("siblings are at the same depth"),
+        }
+        if let Some((separator, second)) = self.children[idx].split(fanout) {
+            self.insert_child(idx, &separator, second);
+        }
+    }
+}
+
+/// B+tree for byte-like keys that stores every key in a leaf without the
+/// prefix it shares with the rest of the leaf, and keeps only as much of
+/// each separator as it takes to tell two neighbouring children apart.
+/// Keys that share long prefixes, like paths, take a fraction of the
+/// memory they take in a `RootNode`, at the cost of rebuilding keys when
+/// they are iterated and rewriting a node whenever its prefix changes.
+pub struct PrefixTree<K, V> {
+    root: PrefixNode<V>,
+    fanout: Fanout,
+    key: PhantomData<K>,
+}
+
+impl<K, V> PrefixTree<K, V>
+where
+    K: ByteKey + fmt::Debug,
+    V: fmt::Debug,
+{
+    pub fn new() -> Self {
+        Self::with_fanout(Fanout::default())
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `fanout` is not valid.
+    pub fn with_fanout(fanout: Fanout) -> Self {
+        assert!(fanout.is_valid(), "fanout too small: {:?}", fanout);
+        PrefixTree {
+            root: PrefixNode::Leaf(PrefixLeaf::new(Vec::new())),
+            fanout,
+            key: PhantomData,
+        }
+    }
+
+    pub fn fanout(&self) -> Fanout {
+        self.fanout
+    }
+
+    pub fn len(&self) -> usize {
+        self.root.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn find(&self, key: &K) -> Option<&V> {
+        self.root.find(key.as_bytes())
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.root.get_mut(key.as_bytes())
+    }
+
+    pub fn insert(&mut self, key: &K, value: V) -> Result<(), NodeError> {
+        let split = self
+            .root
+            .insert(key.as_bytes(), value, self.fanout)
+            .ok_or(NodeError::Duplicated)?;
+        if let Some((separator, second)) = split {
+            let first = std::mem::replace(
+                &mut self.root,
+                PrefixNode::Leaf(PrefixLeaf::new(Vec::new())),
+            );
+            self.root = PrefixNode::Branch(PrefixBranch::new(vec![separator], vec![first, second]));
+        }
+        Ok(())
+    }
+
+    pub fn update(&mut self, key: &K, value: V) -> Result<(), NodeError> {
+        let stored = self.get_mut(key).ok_or(NodeError::NotFound)?;
+        *stored = value;
+        Ok(())
+    }
+
+    pub fn remove(&mut self, key: &K) -> Result<V, NodeError> {
+        let value = self
+            .root
+            .remove(key.as_bytes(), self.fanout)
+            .ok_or(NodeError::NotFound)?;
+        while let PrefixNode::Branch(node) = &mut self.root {
+            if node.children.len() != 1 {
+                break;
+            }
+            let child = node.children.remove(0);
+            self.root = child;
+        }
+        Ok(value)
+    }
+
+    /// Entries within `range` in key order. Keys are rebuilt from their
+    /// prefix and suffix as they are visited.
+    pub fn range<R>(&self, range: R) -> PrefixRange<'_, K, V>
+    where
+        R: RangeBounds<K>,
+    {
+        fn as_bytes<K: ByteKey>(bound: Bound<&K>) -> Bound<&[u8]> {
+            match bound {
+                Bound::Included(key) => Bound::Included(key.as_bytes()),
+                Bound::Excluded(key) => Bound::Excluded(key.as_bytes()),
+                Bound::Unbounded => Bound::Unbounded,
+            }
+        }
+
+        Box::new(
+            self.root
+                .range(as_bytes(range.start_bound()), as_bytes(range.end_bound()))
+                .map(|(key, value)| (K::from_bytes(key), value)),
+        )
+    }
+
+    pub fn iter(&self) -> PrefixRange<'_, K, V> {
+        self.range::<std::ops::RangeFull>(..)
+    }
+}
+
+impl<K, V> Default for PrefixTree<K, V>
+where
+    K: ByteKey + fmt::Debug,
+    V: fmt::Debug,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,67 +1,600 @@
 use super::*;
+use std::{
+    cmp::Ordering,
+    iter::FromIterator,
+    ops::{Bound, RangeBounds},
+};
 
-pub struct RootNode<K, V, const N: usize>
+pub struct RootNode<K, V, C = Natural>
 where
     K: fmt::Debug,
     V: fmt::Debug,
 {
-    root: IntermediateNode<K, V, N>,
+    root: Arc<IntermediateNode<K, V, C>>,
 }
 
-impl<K, V, const N: usize> RootNode<K, V, N>
+impl<K, V> RootNode<K, V>
 where
     K: 'static + fmt::Debug + Clone + Ord,
     V: 'static + fmt::Debug + Clone,
 {
     pub fn new() -> Self {
-        RootNode {
-            root: IntermediateNode::new(Vec::new()),
-        }
+        Self::with_fanout(Fanout::default())
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `fanout` is not valid.
+    pub fn with_fanout(fanout: Fanout) -> Self {
+        Self::with_collation(fanout, Natural)
+    }
+
+    /// Builds a densely packed tree bottom-up from entries in strictly
+    /// ascending key order.
+    pub fn from_sorted_iter<I>(fanout: Fanout, iter: I) -> Result<Self, NodeError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        Self::from_sorted_iter_with(fanout, Natural, iter)
     }
 }
 
-impl<K, V, const N: usize> Node<K, V, N> for RootNode<K, V, N>
+impl<K, V, C> RootNode<K, V, C>
 where
     K: 'static + fmt::Debug + Clone + Ord,
     V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone,
 {
-    fn find(&self, key: &K) -> Option<&V> {
-        self.root.find(key)
+    /// Creates a tree ordering its keys by `collation` instead of their
+    /// `Ord` implementation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fanout` is not valid.
+    pub fn with_collation(fanout: Fanout, collation: C) -> Self {
+        assert!(fanout.is_valid(), "fanout too small: {:?}", fanout);
+        RootNode {
+            root: Arc::new(IntermediateNode::new(Vec::new(), fanout, collation)),
+        }
+    }
+
+    /// Builds a densely packed tree bottom-up from entries in strictly
+    /// ascending order by `collation`.
+    pub fn from_sorted_iter_with<I>(
+        fanout: Fanout,
+        collation: C,
+        iter: I,
+    ) -> Result<Self, NodeError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        if !fanout.is_valid() {
+            return Err(NodeError::InvalidFanout);
+        }
+        let kv_series: Vec<(K, V)> = iter.into_iter().collect();
+        for pair in kv_series.windows(2) {
+            match collation.compare(&pair[0].0, &pair[1].0) {
+                Ordering::Less => {}
+                Ordering::Equal => return Err(NodeError::Duplicated),
+                Ordering::Greater => return Err(NodeError::Unsorted),
+            }
+        }
+        if kv_series.is_empty() {
+            return Ok(Self::with_collation(fanout, collation));
+        }
+
+        let mut level = pack(kv_series, fanout.leaf)
+            .into_iter()
+            .map(|kv_series| {
+                Child::Leaf(LeafNode::new(kv_series, fanout, collation.clone())).keyed()
+            })
+            .collect::<Vec<_>>();
+
+        while level.len() > fanout.intermediate {
+            level = pack(level, fanout.intermediate)
+                .into_iter()
+                .map(|children| {
+                    Child::Intermediate(IntermediateNode::new(children, fanout, collation.clone()))
+                        .keyed()
+                })
+                .collect();
+        }
+
+        Ok(RootNode {
+            root: Arc::new(IntermediateNode::new(level, fanout, collation)),
+        })
+    }
+
+    pub fn fanout(&self) -> Fanout {
+        self.root.fanout()
     }
 
-    fn insert(&mut self, key: &K, value: V) -> Result<(), NodeError<K, V, N>> {
-        let result = self.root.insert(key, value);
-        if let Err(NodeError::Overflow((first_last_key, second_last_key, second_node))) = result {
-            let old_root = std::mem::take(self);
-            self.root = IntermediateNode::new(vec![
-                (first_last_key, Box::new(old_root.root)),
-                (second_last_key, second_node),
-            ]);
+    pub fn collation(&self) -> &C {
+        self.root.collation()
+    }
+
+    /// An empty tree with the same fanout and collation as this one.
+    fn emptied(&self) -> Self {
+        Self::with_collation(self.fanout(), self.collation().clone())
+    }
+
+    /// Looks `key` up once for reading or writing its value in place. The
+    /// entry remembers the way down to where the key was found or belongs,
+    /// so neither taking the value nor inserting it compares keys again.
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, C> {
+        let mut slot = Slot::default();
+        self.root.locate(&key, &mut slot);
+        if slot.occupied {
+            let value = Arc::make_mut(&mut self.root).value_at_mut(&slot.path);
+            Entry::Occupied(OccupiedEntry::new(key, value))
         } else {
-            result?;
+            Entry::Vacant(VacantEntry::new(key, slot, self))
+        }
+    }
+
+    /// Inserts an entry at `slot`, found for its key by `entry`, and borrows
+    /// its value. Only when the leaf was full, and nodes split on the way up,
+    /// is the value reached by walking down along its new path once more.
+    pub(super) fn insert_at(&mut self, mut slot: Slot, key: &K, value: V) -> &mut V {
+        if slot.has_room {
+            return Arc::make_mut(&mut self.root).place_at(&slot.path, key, value);
+        }
+        let overflow = Arc::make_mut(&mut self.root).insert_at(&mut slot.path, key, value);
+        if overflow.is_some() {
+            let width = self.root.width();
+            if slot.path[0] >= width {
+                slot.path[0] -= width;
+                slot.path.insert(0, 1);
+            } else {
+                slot.path.insert(0, 0);
+            }
         }
+        self.link_overflow(overflow);
+        Arc::make_mut(&mut self.root).value_at_mut(&slot.path)
+    }
+
+    pub fn insert(&mut self, key: &K, value: V) -> Result<(), NodeError> {
+        let overflow = Arc::make_mut(&mut self.root).insert(key, value)?;
+        self.link_overflow(overflow);
         Ok(())
     }
 
-    fn update(&mut self, key: &K, value: V) -> Result<(), NodeError<K, V, N>> {
-        self.root.update(key, value)
+    /// Grows the tree by a level if the root split.
+    fn link_overflow(&mut self, overflow: Option<Overflow<K, V, C>>) {
+        if let Some((first_last_key, second)) = overflow {
+            let old_root = std::mem::replace(self, self.emptied());
+            let (fanout, collation) = (old_root.fanout(), old_root.collation().clone());
+            let old_root = Child::Intermediate(Arc::unwrap_or_clone(old_root.root));
+            self.root = Arc::new(IntermediateNode::new(
+                vec![(first_last_key, Arc::new(old_root)), second],
+                fanout,
+                collation,
+            ));
+        }
+    }
+
+    pub fn update(&mut self, key: &K, value: V) -> Result<(), NodeError> {
+        Arc::make_mut(&mut self.root).update(key, value)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Result<(), NodeError> {
+        self.take(key).map(|_| ())
+    }
+
+    fn take(&mut self, key: &K) -> Result<(K, V), NodeError> {
+        let root = Arc::make_mut(&mut self.root);
+        let pair = root.take(key)?;
+        root.collapse();
+        Ok(pair)
+    }
+
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        let key = self.first()?.0.clone();
+        self.take(&key).ok()
+    }
+
+    pub fn pop_last(&mut self) -> Option<(K, V)> {
+        let key = self.last()?.0.clone();
+        self.take(&key).ok()
+    }
+
+    /// Moves every entry from `key` on into a new tree. Only the nodes along
+    /// the cut are split; all subtrees on either side of it are handed over
+    /// whole.
+    pub fn split_off(&mut self, key: &K) -> Self {
+        self.split_at(key, false)
+    }
+
+    /// Moves the entries from `key` on, or those above it if `inclusive` is
+    /// set, into a new tree.
+    fn split_at(&mut self, key: &K, inclusive: bool) -> Self {
+        let root = Arc::make_mut(&mut self.root);
+        let mut second = RootNode {
+            root: Arc::new(root.split_off(key, inclusive)),
+        };
+        root.repair_edge(true);
+        Arc::make_mut(&mut second.root).repair_edge(false);
+        second
+    }
+
+    /// Moves every entry of `other` into this tree, leaving `other` empty.
+    /// When all keys of one tree lie below those of the other and both share
+    /// a fanout, the lower tree is grafted onto the edge of the taller one.
+    /// Otherwise both are merged and rebuilt, and values from `other` win for
+    /// keys found in both. Entries of a tree with another collation are
+    /// inserted one by one.
+    pub fn append(&mut self, other: &mut Self) {
+        let fanout = self.fanout();
+        let second = std::mem::replace(other, other.emptied());
+        let mut first = std::mem::replace(self, self.emptied());
+        let is_below = |first: &Self, second: &Self| match (first.last(), second.first()) {
+            (Some((last_key, _)), Some((first_key, _))) => {
+                first.collation().lt(last_key, first_key)
+            }
+            _ => true,
+        };
+
+        *self = if second.collation().name() != first.collation().name() {
+            first.extend(second);
+            first
+        } else if second.fanout() != fanout {
+            Self::merge(fanout, first, second)
+        } else if is_below(&first, &second) {
+            Self::concat(first, second)
+        } else if is_below(&second, &first) {
+            Self::concat(second, first)
+        } else {
+            Self::merge(fanout, first, second)
+        };
+    }
+
+    /// Joins two trees of the same fanout where every key of `first` is below
+    /// every key of `second`, attaching the root's children of the shorter
+    /// tree to the node at the same height along the edge of the taller one.
+    fn concat(first: Self, second: Self) -> Self {
+        if second.is_empty() {
+            return first;
+        } else if first.is_empty() {
+            return second;
+        }
+
+        let (fanout, collation) = (first.fanout(), first.collation().clone());
+        let (first_height, second_height) = (first.root.height(), second.root.height());
+        let (mut root, second_node) = if first_height >= second_height {
+            let mut root = first.root;
+            let children = Arc::unwrap_or_clone(second.root).into_children();
+            let second_node =
+                Arc::make_mut(&mut root).graft(first_height - second_height, children, true);
+            (root, second_node)
+        } else {
+            let mut root = second.root;
+            let children = Arc::unwrap_or_clone(first.root).into_children();
+            let second_node =
+                Arc::make_mut(&mut root).graft(second_height - first_height, children, false);
+            (root, second_node)
+        };
+
+        if let Some(second_node) = second_node {
+            root = Arc::new(IntermediateNode::new(
+                vec![
+                    Child::Intermediate(Arc::unwrap_or_clone(root)).keyed(),
+                    Child::Intermediate(second_node).keyed(),
+                ],
+                fanout,
+                collation,
+            ));
+        }
+        Arc::make_mut(&mut root).collapse();
+        RootNode { root }
+    }
+
+    /// Rebuilds a tree from the entries of both, preferring the values of
+    /// `second` for keys found in both.
+    fn merge(fanout: Fanout, first: Self, second: Self) -> Self {
+        let collation = first.collation().clone();
+        let mut kv_series = Vec::with_capacity(first.len() + second.len());
+        let mut first = Node::collect(&first).into_iter().peekable();
+        let mut second = Node::collect(&second).into_iter().peekable();
+        while let (Some((first_key, _)), Some((second_key, _))) = (first.peek(), second.peek()) {
+            match collation.compare(first_key, second_key) {
+                Ordering::Less => kv_series.extend(first.next()),
+                Ordering::Greater => kv_series.extend(second.next()),
+                Ordering::Equal => {
+                    first.next();
+                    kv_series.extend(second.next());
+                }
+            }
+        }
+        kv_series.extend(first);
+        kv_series.extend(second);
+        Self::from_sorted_iter_with(fanout, collation, kv_series)
+            .expect("merged entries are sorted")
+    }
+
+    /// Removes every entry within `range` and returns how many there were.
+    /// The range is cut out with two splits and the rest joined again, so
+    /// subtrees lying entirely inside it are dropped whole.
+    pub fn remove_range<R>(&mut self, range: R) -> usize
+    where
+        R: RangeBounds<K>,
+    {
+        let mut removed = match range.start_bound() {
+            Bound::Included(start) => self.split_at(start, false),
+            Bound::Excluded(start) => self.split_at(start, true),
+            Bound::Unbounded => std::mem::replace(self, self.emptied()),
+        };
+        let rest = match range.end_bound() {
+            Bound::Included(end) => removed.split_at(end, true),
+            Bound::Excluded(end) => removed.split_at(end, false),
+            Bound::Unbounded => self.emptied(),
+        };
+        let first = std::mem::replace(self, self.emptied());
+        *self = Self::concat(first, rest);
+        removed.len()
+    }
+
+    /// Walks the whole tree and reports every broken structural invariant.
+    pub fn check(&self) -> CheckReport<K> {
+        let mut checker = Checker::new();
+        let entries = self.root.check(&mut checker, 0, None, true);
+        checker.finish(entries)
+    }
+
+    pub fn stats(&self) -> TreeStats {
+        let mut stats = TreeStats::new();
+        self.root.collect_stats(&mut stats, 0);
+        if stats.leaves() > 0 {
+            stats.leaf_fill_factor =
+                stats.entries as f64 / (stats.leaves() * self.fanout().leaf) as f64;
+        }
+        stats
+    }
+
+    /// Returns a view of the tree as it is now, which later writes to this
+    /// tree do not affect. Taking it only bumps a reference count; nodes are
+    /// copied lazily when a write reaches one that is still shared.
+    pub fn snapshot(&self) -> Snapshot<K, V, C> {
+        Snapshot::new(self.clone())
+    }
+
+    pub fn len(&self) -> usize {
+        self.root.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.len() == 0
+    }
+
+    /// Number of keys strictly less than `key`.
+    pub fn rank(&self, key: &K) -> usize {
+        self.root.rank(key, false)
+    }
+
+    /// Entry at position `idx` in key order.
+    pub fn nth(&self, idx: usize) -> Option<(&K, &V)> {
+        self.root.nth(idx)
+    }
+
+    /// Number of keys within `range`, without visiting them.
+    pub fn count_range<R>(&self, range: R) -> usize
+    where
+        R: RangeBounds<K>,
+    {
+        let from = match range.start_bound() {
+            Bound::Included(start) => self.root.rank(start, false),
+            Bound::Excluded(start) => self.root.rank(start, true),
+            Bound::Unbounded => 0,
+        };
+        let to = match range.end_bound() {
+            Bound::Included(end) => self.root.rank(end, true),
+            Bound::Excluded(end) => self.root.rank(end, false),
+            Bound::Unbounded => self.root.len(),
+        };
+        to.saturating_sub(from)
+    }
+
+    pub fn range<R>(&self, range: R) -> Range<'_, K, V>
+    where
+        R: RangeBounds<K>,
+    {
+        self.root.range(range.start_bound(), range.end_bound())
     }
 
-    fn remove(&mut self, key: &K) -> Result<(), NodeError<K, V, N>> {
-        self.root.remove(key)
+    /// All entries in key order.
+    pub fn iter(&self) -> Range<'_, K, V> {
+        self.root.range(Bound::Unbounded, Bound::Unbounded)
+    }
+
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K> + '_ {
+        self.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> + '_ {
+        self.iter().map(|(_, value)| value)
+    }
+}
+
+impl<K, V, C> sealed::Sealed for RootNode<K, V, C>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+}
+
+impl<K, V, C> Node<K, V> for RootNode<K, V, C>
+where
+    K: 'static + fmt::Debug + Clone + Ord,
+    V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone,
+{
+    fn find(&self, key: &K) -> Option<&V> {
+        self.root.find(key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        Arc::make_mut(&mut self.root).get_mut(key)
     }
 
     fn collect(&self) -> Vec<(K, V)> {
         self.root.collect()
     }
+
+    fn range<'a>(&'a self, start: Bound<&K>, end: Bound<&K>) -> Range<'a, K, V> {
+        self.root.range(start, end)
+    }
+}
+
+impl<K, V, C> Clone for RootNode<K, V, C>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    /// Clones share all nodes with the original until either is written to.
+    fn clone(&self) -> Self {
+        RootNode {
+            root: Arc::clone(&self.root),
+        }
+    }
 }
 
-impl<K, V, const N: usize> Default for RootNode<K, V, N>
+impl<K, V, C> Default for RootNode<K, V, C>
 where
     K: 'static + fmt::Debug + Clone + Ord,
     V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone + Default,
 {
     fn default() -> Self {
-        Self::new()
+        Self::with_collation(Fanout::default(), C::default())
+    }
+}
+
+impl<K, V, C> IntoIterator for RootNode<K, V, C>
+where
+    K: 'static + fmt::Debug + Clone + Ord,
+    V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone,
+{
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V, C>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter::new(Arc::unwrap_or_clone(self.root))
+    }
+}
+
+impl<'a, K, V, C> IntoIterator for &'a RootNode<K, V, C>
+where
+    K: 'static + fmt::Debug + Clone + Ord,
+    V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone,
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Range<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Builds a densely packed tree with the default fanout. As with repeated
+/// inserts into a map, the last value given for a key wins.
+impl<K, V, C> FromIterator<(K, V)> for RootNode<K, V, C>
+where
+    K: 'static + fmt::Debug + Clone + Ord,
+    V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone + Default,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let collation = C::default();
+        let mut kv_series: Vec<(K, V)> = iter.into_iter().collect();
+        kv_series.sort_by(|(first, _), (second, _)| collation.compare(first, second));
+        let mut deduplicated: Vec<(K, V)> = Vec::with_capacity(kv_series.len());
+        for (key, value) in kv_series {
+            match deduplicated.last_mut() {
+                Some(last) if collation.compare(&last.0, &key) == Ordering::Equal => last.1 = value,
+                _ => deduplicated.push((key, value)),
+            }
+        }
+        Self::from_sorted_iter_with(Fanout::default(), collation, deduplicated)
+            .expect("entries are sorted and unique and the default fanout is valid")
+    }
+}
+
+/// Inserts every entry, overwriting the value of keys already present.
+impl<K, V, C> Extend<(K, V)> for RootNode<K, V, C>
+where
+    K: 'static + fmt::Debug + Clone + Ord,
+    V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        for (key, value) in iter {
+            match self.get_mut(&key) {
+                Some(stored) => *stored = value,
+                None => self
+                    .insert(&key, value)
+                    .expect("the key has just been looked up"),
+            }
+        }
+    }
+}
+
+/// Trees are equal when they hold the same entries, whatever their fanout
+/// or shape. Keys are compared with the collation, so under `CaseInsensitive`
+/// a tree holding "a" equals one holding "A". Trees ordered by collations of
+/// different names are never equal.
+impl<K, V, C> PartialEq for RootNode<K, V, C>
+where
+    K: 'static + fmt::Debug + Clone + Ord,
+    V: 'static + fmt::Debug + Clone + PartialEq,
+    C: Comparator<K> + Clone,
+{
+    fn eq(&self, other: &Self) -> bool {
+        let collation = self.collation();
+        Arc::ptr_eq(&self.root, &other.root)
+            || (self.len() == other.len()
+                && collation.name() == other.collation().name()
+                && self
+                    .iter()
+                    .zip(other.iter())
+                    .all(|((key, value), (other_key, other_value))| {
+                        collation.compare(key, other_key) == Ordering::Equal && value == other_value
+                    }))
+    }
+}
+
+impl<K, V, C> Eq for RootNode<K, V, C>
+where
+    K: 'static + fmt::Debug + Clone + Ord,
+    V: 'static + fmt::Debug + Clone + Eq,
+    C: Comparator<K> + Clone,
+{
+}
+
+/// Prints the tree level by level: every child is listed under the separator
+/// key it is filed under, followed by the entries of each leaf. A precision,
+/// as in `{:.3?}`, limits how many entries are shown per leaf.
+impl<K, V, C> fmt::Debug for RootNode<K, V, C>
+where
+    K: fmt::Debug + Ord,
+    V: fmt::Debug,
+    C: Comparator<K> + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fanout = self.root.fanout();
+        writeln!(
+            f,
+            "RootNode<{}, {}> ({})",
+            fanout.leaf,
+            fanout.intermediate,
+            self.root.len()
+        )?;
+        self.root.fmt_tree(f, 1, f.precision())
     }
 }
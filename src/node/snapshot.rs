@@ -0,0 +1,58 @@
+use super::*;
+use std::ops::Deref;
+
+/// Read-only view of a `RootNode` at the time `RootNode::snapshot` was
+/// called. It can be moved to or shared with other threads while the
+/// original keeps being written to.
+pub struct Snapshot<K, V, C = Natural>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    root_node: RootNode<K, V, C>,
+}
+
+impl<K, V, C> Snapshot<K, V, C>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    pub(super) fn new(root_node: RootNode<K, V, C>) -> Self {
+        Snapshot { root_node }
+    }
+}
+
+impl<K, V, C> Deref for Snapshot<K, V, C>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    type Target = RootNode<K, V, C>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.root_node
+    }
+}
+
+impl<K, V, C> Clone for Snapshot<K, V, C>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn clone(&self) -> Self {
+        Snapshot {
+            root_node: self.root_node.clone(),
+        }
+    }
+}
+
+impl<K, V, C> fmt::Debug for Snapshot<K, V, C>
+where
+    K: fmt::Debug + Ord,
+    V: fmt::Debug,
+    C: Comparator<K> + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.root_node.fmt(f)
+    }
+}
@@ -0,0 +1,53 @@
+use std::fmt;
+
+/// Shape of a tree as returned by `RootNode::stats`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeStats {
+    /// Number of levels below the root, leaves included.
+    pub height: usize,
+    /// Number of nodes at each level, starting with the root.
+    pub nodes_per_level: Vec<usize>,
+    /// Entries per leaf relative to the fanout, averaged over all leaves.
+    pub leaf_fill_factor: f64,
+    pub entries: usize,
+}
+
+impl TreeStats {
+    pub(super) fn new() -> Self {
+        TreeStats {
+            height: 0,
+            nodes_per_level: vec![1],
+            leaf_fill_factor: 0.0,
+            entries: 0,
+        }
+    }
+
+    pub(super) fn count_node(&mut self, depth: usize) {
+        if self.nodes_per_level.len() <= depth {
+            self.nodes_per_level.resize(depth + 1, 0);
+        }
+        self.nodes_per_level[depth] += 1;
+        self.height = self.height.max(depth);
+    }
+
+    pub fn leaves(&self) -> usize {
+        if self.height == 0 {
+            0
+        } else {
+            self.nodes_per_level[self.height]
+        }
+    }
+}
+
+impl fmt::Display for TreeStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} entries, height {}, nodes per level {:?}, leaf fill {:.1}%",
+            self.entries,
+            self.height,
+            self.nodes_per_level,
+            self.leaf_fill_factor * 100.0
+        )
+    }
+}
@@ -0,0 +1,385 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    cell::RefCell,
+    fs::OpenOptions,
+    ops::{Bound, RangeBounds},
+    path::Path,
+};
+
+use page::{Header, IntermediatePage, LeafPage, Page};
+pub use page::{PageId, MAX_ENTRY_SIZE, PAGE_SIZE};
+use pool::BufferPool;
+pub use range::PagedRange;
+
+mod page;
+mod pool;
+mod range;
+
+#[derive(thiserror::Error, Debug)]
+pub enum PagedError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Serde(#[from] bincode::Error),
+    #[error("file is not a paged tree")]
+    BadMagic,
+    #[error("file was written with {0}-byte pages")]
+    PageSizeMismatch(usize),
+    #[error("page {0} is corrupted")]
+    Corrupted(PageId),
+    #[error("page of {0} bytes does not fit")]
+    PageOverflow(usize),
+    #[error("entry of {0} bytes exceeds the size limit")]
+    EntryTooLarge(usize),
+    #[error("key duplicated")]
+    Duplicated,
+    #[error("key not found")]
+    NotFound,
+}
+
+/// B+tree stored in fixed-size pages of a single file, of which only a
+/// bounded number are held in memory at a time. Opening an existing file
+/// reads nothing but its header, and values are returned by copy since the
+/// page they live in may be evicted at any point.
+///
+/// Changes are durable once `flush` returns, and not before. Modified pages
+/// evicted in between are written in place while the header is only written
+/// by `flush`, so a crash between two flushes may leave the file in a state
+/// that cannot be opened. The file is not meant to survive crashes without a
+/// log kept alongside it.
+pub struct PagedTree<K, V>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    pool: RefCell<BufferPool<K, V>>,
+}
+
+impl<K, V> PagedTree<K, V>
+where
+    K: Serialize + DeserializeOwned + Clone + Ord,
+    V: Serialize + DeserializeOwned + Clone,
+{
+    /// Opens the tree stored at `file_path`, creating an empty one if the
+    /// file does not exist. At most `pool_capacity` pages are cached.
+    pub fn open(file_path: &Path, pool_capacity: usize) -> Result<Self, PagedError> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(file_path)?;
+
+        let pool = if file.metadata()?.len() == 0 {
+            let mut pool = BufferPool::new(file, Header::new(), pool_capacity);
+            pool.header.root = pool.allocate(Page::Leaf(LeafPage::new(None, None)))?;
+            pool.flush()?;
+            pool
+        } else {
+            let header = Header::decode(&BufferPool::<K, V>::read_raw(&mut file, 0)?)?;
+            BufferPool::new(file, header, pool_capacity)
+        };
+
+        Ok(PagedTree {
+            pool: RefCell::new(pool),
+        })
+    }
+
+    /// Writes every modified page and the header back and syncs the file.
+    /// Dropping the tree does the same but cannot report a failure, so call
+    /// this to know whether the changes reached the disk.
+    pub fn flush(&self) -> Result<(), PagedError> {
+        self.pool.borrow_mut().flush()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pool.borrow().header.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn find(&self, key: &K) -> Result<Option<V>, PagedError> {
+        let pool = &mut *self.pool.borrow_mut();
+        let id = match Self::lower_leaf(pool, Bound::Included(key))? {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+        let leaf = pool.get(id)?.leaf(id)?;
+        Ok(leaf
+            .search(key)
+            .ok()
+            .map(|idx| leaf.kv_series()[idx].1.clone()))
+    }
+
+    pub fn insert(&mut self, key: &K, value: V) -> Result<(), PagedError> {
+        let pool = self.pool.get_mut();
+        Self::put(pool, key, value, false)?;
+        pool.header.len += 1;
+        Ok(())
+    }
+
+    pub fn update(&mut self, key: &K, value: V) -> Result<(), PagedError> {
+        Self::put(self.pool.get_mut(), key, value, true)
+    }
+
+    /// Removes `key` and returns the value it held.
+    pub fn remove(&mut self, key: &K) -> Result<V, PagedError> {
+        let pool = self.pool.get_mut();
+        let value = Self::remove_from(pool, pool.header.root, key)?;
+        pool.header.len -= 1;
+
+        loop {
+            let root = pool.header.root;
+            let sole = match pool.get(root)? {
+                Page::Intermediate(node) if node.children().len() == 1 => node.children()[0].1,
+                _ => break,
+            };
+            pool.header.root = sole;
+            pool.free(root)?;
+        }
+        Ok(value)
+    }
+
+    pub fn range<R>(&self, range: R) -> Result<PagedRange<'_, K, V>, PagedError>
+    where
+        R: RangeBounds<K>,
+    {
+        PagedRange::new(
+            self,
+            range.start_bound().cloned(),
+            range.end_bound().cloned(),
+        )
+    }
+
+    pub fn first(&self) -> Result<Option<(K, V)>, PagedError> {
+        self.range(..)?.next().transpose()
+    }
+
+    pub fn last(&self) -> Result<Option<(K, V)>, PagedError> {
+        self.range(..)?.next_back().transpose()
+    }
+
+    /// Follows `pick` from the root down to a leaf, stopping early if it
+    /// finds no child to descend into.
+    fn descend<F>(pool: &mut BufferPool<K, V>, mut pick: F) -> Result<Option<PageId>, PagedError>
+    where
+        F: FnMut(&IntermediatePage<K>) -> Option<usize>,
+    {
+        let mut id = pool.header.root;
+        loop {
+            id = match pool.get(id)? {
+                Page::Leaf(_) => return Ok(Some(id)),
+                Page::Intermediate(node) if !node.children().is_empty() => match pick(node) {
+                    Some(idx) => node.children()[idx].1,
+                    None => return Ok(None),
+                },
+                _ => return Err(PagedError::Corrupted(id)),
+            };
+        }
+    }
+
+    /// First leaf that may hold keys after `start`, or `None` if every key
+    /// in the tree is before it.
+    fn lower_leaf(
+        pool: &mut BufferPool<K, V>,
+        start: Bound<&K>,
+    ) -> Result<Option<PageId>, PagedError> {
+        Self::descend(pool, |node| match start {
+            Bound::Included(key) | Bound::Excluded(key) => node.child_idx(key),
+            Bound::Unbounded => Some(0),
+        })
+    }
+
+    /// Last leaf that may hold keys before `end`.
+    fn upper_leaf(
+        pool: &mut BufferPool<K, V>,
+        end: Bound<&K>,
+    ) -> Result<Option<PageId>, PagedError> {
+        Self::descend(pool, |node| {
+            let last = node.children().len() - 1;
+            match end {
+                Bound::Included(key) | Bound::Excluded(key) => {
+                    Some(node.child_idx(key).unwrap_or(last))
+                }
+                Bound::Unbounded => Some(last),
+            }
+        })
+    }
+
+    fn put(
+        pool: &mut BufferPool<K, V>,
+        key: &K,
+        value: V,
+        replace: bool,
+    ) -> Result<(), PagedError> {
+        // Checked before anything is changed on the way down, so that a
+        // rejected entry leaves the tree as it was.
+        let entry_size = LeafPage::entry_size(key, &value)?;
+        if entry_size > MAX_ENTRY_SIZE {
+            return Err(PagedError::EntryTooLarge(entry_size));
+        }
+
+        let root = pool.header.root;
+        if let Some((left_bound, right_bound, right_id)) =
+            Self::put_into(pool, root, key, value, entry_size, replace)?
+        {
+            let node = IntermediatePage::new(vec![(left_bound, root), (right_bound, right_id)])?;
+            pool.header.root = pool.allocate(Page::Intermediate(node))?;
+        }
+        Ok(())
+    }
+
+    /// Inserts into or updates the subtree at `id` an entry whose encoded
+    /// size is `entry_size`. If the page had to be split, returns the new
+    /// bounds of both halves and the id of the right one.
+    fn put_into(
+        pool: &mut BufferPool<K, V>,
+        id: PageId,
+        key: &K,
+        value: V,
+        entry_size: usize,
+        replace: bool,
+    ) -> Result<Option<(K, K, PageId)>, PagedError> {
+        let (idx, child, extend) = match pool.get(id)? {
+            Page::Leaf(_) => return Self::put_into_leaf(pool, id, key, value, entry_size, replace),
+            Page::Intermediate(node) => match node.child_idx(key) {
+                Some(idx) => (idx, node.children()[idx].1, false),
+                None if replace => return Err(PagedError::NotFound),
+                None => {
+                    let idx = node.children().len() - 1;
+                    (idx, node.children()[idx].1, true)
+                }
+            },
+            Page::Free(_) => return Err(PagedError::Corrupted(id)),
+        };
+
+        if extend {
+            pool.intermediate_mut(id)?.set_bound(idx, key.clone())?;
+        }
+
+        let split = Self::put_into(pool, child, key, value, entry_size, replace)?;
+        if split.is_none() && !extend {
+            return Ok(None);
+        }
+
+        let node = pool.intermediate_mut(id)?;
+        if let Some((left_bound, right_bound, right_id)) = split {
+            node.set_bound(idx, left_bound)?;
+            node.insert(idx + 1, right_bound, right_id)?;
+        }
+
+        // A raised bound may outgrow the page as well as a new child.
+        let right = match node.split() {
+            Some(right) => right,
+            None => return Ok(None),
+        };
+        let left_bound = node.children()[node.children().len() - 1].0.clone();
+        let right_bound = right.children()[right.children().len() - 1].0.clone();
+        let right_id = pool.allocate(Page::Intermediate(right))?;
+        Ok(Some((left_bound, right_bound, right_id)))
+    }
+
+    fn put_into_leaf(
+        pool: &mut BufferPool<K, V>,
+        id: PageId,
+        key: &K,
+        value: V,
+        entry_size: usize,
+        replace: bool,
+    ) -> Result<Option<(K, K, PageId)>, PagedError> {
+        let leaf = pool.leaf_mut(id)?;
+        match (leaf.search(key), replace) {
+            (Ok(idx), true) => {
+                leaf.replace(idx, value, entry_size);
+            }
+            (Err(idx), false) => leaf.insert(idx, key.clone(), value, entry_size),
+            (Ok(_), false) => return Err(PagedError::Duplicated),
+            (Err(_), true) => return Err(PagedError::NotFound),
+        }
+
+        let mut right = match leaf.split() {
+            Some(right) => right,
+            None => return Ok(None),
+        };
+        right.prev = Some(id);
+        let left_bound = leaf.kv_series()[leaf.kv_series().len() - 1].0.clone();
+        let right_bound = right.kv_series()[right.kv_series().len() - 1].0.clone();
+        let next = right.next;
+
+        let right_id = pool.allocate(Page::Leaf(right))?;
+        pool.leaf_mut(id)?.next = Some(right_id);
+        if let Some(next) = next {
+            pool.leaf_mut(next)?.prev = Some(right_id);
+        }
+        Ok(Some((left_bound, right_bound, right_id)))
+    }
+
+    fn remove_from(pool: &mut BufferPool<K, V>, id: PageId, key: &K) -> Result<V, PagedError> {
+        let (idx, child) = match pool.get(id)? {
+            Page::Leaf(_) => {
+                let leaf = pool.leaf_mut(id)?;
+                let idx = leaf.search(key).map_err(|_| PagedError::NotFound)?;
+                return Ok(leaf.remove(idx).1);
+            }
+            Page::Intermediate(node) => match node.child_idx(key) {
+                Some(idx) => (idx, node.children()[idx].1),
+                None => return Err(PagedError::NotFound),
+            },
+            Page::Free(_) => return Err(PagedError::Corrupted(id)),
+        };
+
+        let value = Self::remove_from(pool, child, key)?;
+        Self::rebalance(pool, id, idx)?;
+        Ok(value)
+    }
+
+    /// Merges the child at `idx` of the node at `id` into a neighbour if it
+    /// fell below a quarter of a page and both fit in one.
+    fn rebalance(pool: &mut BufferPool<K, V>, id: PageId, idx: usize) -> Result<(), PagedError> {
+        let (child, left_idx, left_id, right_id) = match pool.get(id)? {
+            Page::Intermediate(node) if node.children().len() > 1 => {
+                let left_idx = idx.min(node.children().len() - 2);
+                (
+                    node.children()[idx].1,
+                    left_idx,
+                    node.children()[left_idx].1,
+                    node.children()[left_idx + 1].1,
+                )
+            }
+            _ => return Ok(()),
+        };
+        if !pool.get(child)?.is_underflow() {
+            return Ok(());
+        }
+        let left_size = pool.get(left_id)?.size();
+        if left_size + pool.get(right_id)?.payload() > PAGE_SIZE {
+            return Ok(());
+        }
+
+        let right = std::mem::replace(pool.get_mut(right_id)?, Page::Free(None));
+        pool.free(right_id)?;
+        if let Some(next) = pool.get_mut(left_id)?.absorb(left_id, right)? {
+            pool.leaf_mut(next)?.prev = Some(left_id);
+        }
+
+        let node = pool.intermediate_mut(id)?;
+        node.set_child(left_idx + 1, left_id);
+        node.remove(left_idx);
+        Ok(())
+    }
+}
+
+impl<K, V> Drop for PagedTree<K, V>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    fn drop(&mut self) {
+        // Errors cannot be reported from here; call `flush` to observe them.
+        // A failure leaves the file as of the last successful flush, apart
+        // from pages evicted since then.
+        let _ = self.pool.get_mut().flush();
+    }
+}
@@ -0,0 +1,432 @@
+use super::*;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{Cursor, Read};
+
+pub const PAGE_SIZE: usize = 4096;
+/// Largest encoded entry a leaf accepts, so that every page holds at least
+/// four entries and a split always leaves two halves that fit.
+pub const MAX_ENTRY_SIZE: usize = (PAGE_SIZE - LEAF_HEADER_SIZE) / 4;
+
+pub type PageId = u64;
+
+const MAGIC: &[u8; 8] = b"DBPAGED2";
+const LEAF_TAG: u8 = 1;
+const INTERMEDIATE_TAG: u8 = 2;
+const FREE_TAG: u8 = 3;
+const LEAF_HEADER_SIZE: usize = 1 + 8 + 8 + 4;
+const INTERMEDIATE_HEADER_SIZE: usize = 1 + 4;
+
+/// Contents of page 0. Page ids start at 1 elsewhere, so 0 stands for "none"
+/// in the encoded links.
+#[derive(Debug, Clone)]
+pub struct Header {
+    pub root: PageId,
+    pub page_count: u64,
+    pub free_head: Option<PageId>,
+    pub len: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum Page<K, V> {
+    Leaf(LeafPage<K, V>),
+    Intermediate(IntermediatePage<K>),
+    Free(Option<PageId>),
+}
+
+#[derive(Debug, Clone)]
+pub struct LeafPage<K, V> {
+    pub prev: Option<PageId>,
+    pub next: Option<PageId>,
+    kv_series: Vec<(K, V)>,
+    /// Encoded size of each entry, worked out once when it is put in.
+    sizes: Vec<usize>,
+    /// Encoded size of the whole page, kept up to date on every change.
+    pub size: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct IntermediatePage<K> {
+    /// Each child paired with an upper bound of the keys below it.
+    children: Vec<(K, PageId)>,
+    /// Encoded size of each child entry.
+    sizes: Vec<usize>,
+    pub size: usize,
+}
+
+fn link(id: Option<PageId>) -> u64 {
+    id.unwrap_or(0)
+}
+
+fn unlink(raw: u64) -> Option<PageId> {
+    if raw == 0 {
+        None
+    } else {
+        Some(raw)
+    }
+}
+
+fn encoded_size<T>(value: &T) -> Result<usize, PagedError>
+where
+    T: Serialize,
+{
+    Ok(bincode::serialized_size(value)? as usize)
+}
+
+/// Index splitting `sizes` into two runs of roughly equal total size, each
+/// with at least one element.
+fn split_point(sizes: &[usize]) -> usize {
+    let half = sizes.iter().sum::<usize>() / 2;
+    let mut total = 0;
+    let idx = sizes
+        .iter()
+        .position(|size| {
+            total += size;
+            total >= half
+        })
+        .unwrap_or(0);
+    (idx + 1).clamp(1, sizes.len() - 1)
+}
+
+impl Header {
+    pub fn new() -> Self {
+        Header {
+            root: 0,
+            page_count: 1,
+            free_head: None,
+            len: 0,
+        }
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, PagedError> {
+        let mut buf = Vec::with_capacity(PAGE_SIZE);
+        buf.extend_from_slice(MAGIC);
+        buf.write_u32::<LittleEndian>(PAGE_SIZE as u32)?;
+        buf.write_u64::<LittleEndian>(self.root)?;
+        buf.write_u64::<LittleEndian>(self.page_count)?;
+        buf.write_u64::<LittleEndian>(link(self.free_head))?;
+        buf.write_u64::<LittleEndian>(self.len as u64)?;
+        buf.resize(PAGE_SIZE, 0);
+        Ok(buf)
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self, PagedError> {
+        let mut reader = Cursor::new(buf);
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(PagedError::BadMagic);
+        }
+        let page_size = reader.read_u32::<LittleEndian>()? as usize;
+        if page_size != PAGE_SIZE {
+            return Err(PagedError::PageSizeMismatch(page_size));
+        }
+        Ok(Header {
+            root: reader.read_u64::<LittleEndian>()?,
+            page_count: reader.read_u64::<LittleEndian>()?,
+            free_head: unlink(reader.read_u64::<LittleEndian>()?),
+            len: reader.read_u64::<LittleEndian>()? as usize,
+        })
+    }
+}
+
+impl<K, V> Page<K, V>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    pub fn encode(&self) -> Result<Vec<u8>, PagedError> {
+        let mut buf = Vec::with_capacity(PAGE_SIZE);
+        match self {
+            Page::Leaf(leaf) => {
+                buf.write_u8(LEAF_TAG)?;
+                buf.write_u64::<LittleEndian>(link(leaf.prev))?;
+                buf.write_u64::<LittleEndian>(link(leaf.next))?;
+                buf.write_u32::<LittleEndian>(leaf.kv_series.len() as u32)?;
+                for (key, value) in &leaf.kv_series {
+                    bincode::serialize_into(&mut buf, key)?;
+                    bincode::serialize_into(&mut buf, value)?;
+                }
+            }
+            Page::Intermediate(node) => {
+                buf.write_u8(INTERMEDIATE_TAG)?;
+                buf.write_u32::<LittleEndian>(node.children.len() as u32)?;
+                for (key, child) in &node.children {
+                    bincode::serialize_into(&mut buf, key)?;
+                    buf.write_u64::<LittleEndian>(*child)?;
+                }
+            }
+            Page::Free(next) => {
+                buf.write_u8(FREE_TAG)?;
+                buf.write_u64::<LittleEndian>(link(*next))?;
+            }
+        }
+        if buf.len() > PAGE_SIZE {
+            return Err(PagedError::PageOverflow(buf.len()));
+        }
+        buf.resize(PAGE_SIZE, 0);
+        Ok(buf)
+    }
+
+    pub fn decode(id: PageId, buf: &[u8]) -> Result<Self, PagedError> {
+        let mut reader = Cursor::new(buf);
+        let page = match reader.read_u8()? {
+            LEAF_TAG => {
+                let prev = unlink(reader.read_u64::<LittleEndian>()?);
+                let next = unlink(reader.read_u64::<LittleEndian>()?);
+                let count = reader.read_u32::<LittleEndian>()? as usize;
+                let mut kv_series = Vec::with_capacity(count);
+                let mut sizes = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let offset = reader.position();
+                    let key = bincode::deserialize_from(&mut reader)?;
+                    let value = bincode::deserialize_from(&mut reader)?;
+                    kv_series.push((key, value));
+                    sizes.push((reader.position() - offset) as usize);
+                }
+                let size = reader.position() as usize;
+                Page::Leaf(LeafPage {
+                    prev,
+                    next,
+                    kv_series,
+                    sizes,
+                    size,
+                })
+            }
+            INTERMEDIATE_TAG => {
+                let count = reader.read_u32::<LittleEndian>()? as usize;
+                let mut children = Vec::with_capacity(count);
+                let mut sizes = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let offset = reader.position();
+                    let key = bincode::deserialize_from(&mut reader)?;
+                    children.push((key, reader.read_u64::<LittleEndian>()?));
+                    sizes.push((reader.position() - offset) as usize);
+                }
+                let size = reader.position() as usize;
+                Page::Intermediate(IntermediatePage {
+                    children,
+                    sizes,
+                    size,
+                })
+            }
+            FREE_TAG => Page::Free(unlink(reader.read_u64::<LittleEndian>()?)),
+            _ => return Err(PagedError::Corrupted(id)),
+        };
+        Ok(page)
+    }
+}
+
+impl<K, V> LeafPage<K, V>
+where
+    K: Serialize + Ord,
+    V: Serialize,
+{
+    /// Empty leaf between `prev` and `next`.
+    pub fn new(prev: Option<PageId>, next: Option<PageId>) -> Self {
+        LeafPage {
+            prev,
+            next,
+            kv_series: Vec::new(),
+            sizes: Vec::new(),
+            size: LEAF_HEADER_SIZE,
+        }
+    }
+
+    pub fn entry_size(key: &K, value: &V) -> Result<usize, PagedError> {
+        Ok(encoded_size(key)? + encoded_size(value)?)
+    }
+
+    pub fn kv_series(&self) -> &[(K, V)] {
+        &self.kv_series
+    }
+
+    pub fn search(&self, key: &K) -> Result<usize, usize> {
+        self.kv_series
+            .binary_search_by(|(stored_key, _)| stored_key.cmp(key))
+    }
+
+    /// Puts an entry whose encoded size is `entry_size` at `idx`.
+    pub fn insert(&mut self, idx: usize, key: K, value: V, entry_size: usize) {
+        self.kv_series.insert(idx, (key, value));
+        self.sizes.insert(idx, entry_size);
+        self.size += entry_size;
+    }
+
+    /// Replaces the value at `idx`, after which the entry is `entry_size`
+    /// bytes long, and returns the old one.
+    pub fn replace(&mut self, idx: usize, value: V, entry_size: usize) -> V {
+        self.size = self.size + entry_size - self.sizes[idx];
+        self.sizes[idx] = entry_size;
+        std::mem::replace(&mut self.kv_series[idx].1, value)
+    }
+
+    pub fn remove(&mut self, idx: usize) -> (K, V) {
+        self.size -= self.sizes.remove(idx);
+        self.kv_series.remove(idx)
+    }
+
+    /// Moves the upper half of the entries, by encoded size, out of this
+    /// leaf if it no longer fits in a page. The new leaf is linked to the
+    /// next one but not yet back to this one.
+    pub fn split(&mut self) -> Option<Self> {
+        if self.size <= PAGE_SIZE {
+            return None;
+        }
+        let idx = split_point(&self.sizes);
+        let sizes = self.sizes.split_off(idx);
+        let moved = sizes.iter().sum::<usize>();
+        self.size -= moved;
+        Some(LeafPage {
+            prev: None,
+            next: self.next,
+            kv_series: self.kv_series.split_off(idx),
+            sizes,
+            size: LEAF_HEADER_SIZE + moved,
+        })
+    }
+}
+
+impl<K> IntermediatePage<K>
+where
+    K: Serialize + Ord,
+{
+    pub fn new(children: Vec<(K, PageId)>) -> Result<Self, PagedError> {
+        let sizes = children
+            .iter()
+            .map(|(key, _)| Self::entry_size(key))
+            .collect::<Result<Vec<_>, _>>()?;
+        let size = INTERMEDIATE_HEADER_SIZE + sizes.iter().sum::<usize>();
+        Ok(IntermediatePage {
+            children,
+            sizes,
+            size,
+        })
+    }
+
+    pub fn entry_size(key: &K) -> Result<usize, PagedError> {
+        Ok(encoded_size(key)? + 8)
+    }
+
+    pub fn children(&self) -> &[(K, PageId)] {
+        &self.children
+    }
+
+    /// Position of the first child that may hold `key`, if any.
+    pub fn child_idx(&self, key: &K) -> Option<usize> {
+        let idx = self.children.partition_point(|(bound, _)| bound < key);
+        if idx < self.children.len() {
+            Some(idx)
+        } else {
+            None
+        }
+    }
+
+    /// Replaces the bound of the child at `idx` and returns the old one.
+    pub fn set_bound(&mut self, idx: usize, bound: K) -> Result<K, PagedError> {
+        let entry_size = Self::entry_size(&bound)?;
+        self.size = self.size + entry_size - self.sizes[idx];
+        self.sizes[idx] = entry_size;
+        Ok(std::mem::replace(&mut self.children[idx].0, bound))
+    }
+
+    pub fn set_child(&mut self, idx: usize, child: PageId) {
+        self.children[idx].1 = child;
+    }
+
+    pub fn insert(&mut self, idx: usize, bound: K, child: PageId) -> Result<(), PagedError> {
+        let entry_size = Self::entry_size(&bound)?;
+        self.children.insert(idx, (bound, child));
+        self.sizes.insert(idx, entry_size);
+        self.size += entry_size;
+        Ok(())
+    }
+
+    pub fn remove(&mut self, idx: usize) -> (K, PageId) {
+        self.size -= self.sizes.remove(idx);
+        self.children.remove(idx)
+    }
+
+    pub fn split(&mut self) -> Option<Self> {
+        if self.size <= PAGE_SIZE {
+            return None;
+        }
+        let idx = split_point(&self.sizes);
+        let sizes = self.sizes.split_off(idx);
+        let moved = sizes.iter().sum::<usize>();
+        self.size -= moved;
+        Some(IntermediatePage {
+            children: self.children.split_off(idx),
+            sizes,
+            size: INTERMEDIATE_HEADER_SIZE + moved,
+        })
+    }
+}
+
+impl<K, V> Page<K, V> {
+    pub fn leaf(&self, id: PageId) -> Result<&LeafPage<K, V>, PagedError> {
+        match self {
+            Page::Leaf(leaf) => Ok(leaf),
+            _ => Err(PagedError::Corrupted(id)),
+        }
+    }
+
+    pub fn leaf_mut(&mut self, id: PageId) -> Result<&mut LeafPage<K, V>, PagedError> {
+        match self {
+            Page::Leaf(leaf) => Ok(leaf),
+            _ => Err(PagedError::Corrupted(id)),
+        }
+    }
+
+    pub fn intermediate_mut(&mut self, id: PageId) -> Result<&mut IntermediatePage<K>, PagedError> {
+        match self {
+            Page::Intermediate(node) => Ok(node),
+            _ => Err(PagedError::Corrupted(id)),
+        }
+    }
+
+    pub fn is_underflow(&self) -> bool {
+        match self {
+            Page::Leaf(leaf) => leaf.size < PAGE_SIZE / 4,
+            Page::Intermediate(node) => node.size < PAGE_SIZE / 4,
+            Page::Free(_) => false,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            Page::Leaf(leaf) => leaf.size,
+            Page::Intermediate(node) => node.size,
+            Page::Free(_) => PAGE_SIZE,
+        }
+    }
+
+    /// Encoded size of the entries alone, without the page header.
+    pub fn payload(&self) -> usize {
+        match self {
+            Page::Leaf(leaf) => leaf.size - LEAF_HEADER_SIZE,
+            Page::Intermediate(node) => node.size - INTERMEDIATE_HEADER_SIZE,
+            Page::Free(_) => 0,
+        }
+    }
+
+    /// Appends the entries of `other`, the right neighbour of this page, and
+    /// returns the leaf following `other` whose back link has to be fixed.
+    pub fn absorb(&mut self, id: PageId, other: Self) -> Result<Option<PageId>, PagedError> {
+        match (self, other) {
+            (Page::Leaf(leaf), Page::Leaf(other)) => {
+                leaf.size += other.size - LEAF_HEADER_SIZE;
+                leaf.kv_series.extend(other.kv_series);
+                leaf.sizes.extend(other.sizes);
+                leaf.next = other.next;
+                Ok(other.next)
+            }
+            (Page::Intermediate(node), Page::Intermediate(other)) => {
+                node.size += other.size - INTERMEDIATE_HEADER_SIZE;
+                node.children.extend(other.children);
+                node.sizes.extend(other.sizes);
+                Ok(None)
+            }
+            _ => Err(PagedError::Corrupted(id)),
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use super::*;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+};
+
+struct Frame<K, V> {
+    id: PageId,
+    page: Page<K, V>,
+    dirty: bool,
+    referenced: bool,
+}
+
+/// Fixed number of decoded pages kept in memory. Pages are evicted with the
+/// clock algorithm and written back only if they were modified.
+pub struct BufferPool<K, V> {
+    file: File,
+    pub header: Header,
+    frames: Vec<Frame<K, V>>,
+    page_table: HashMap<PageId, usize>,
+    hand: usize,
+    capacity: usize,
+}
+
+impl<K, V> BufferPool<K, V>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    pub fn new(file: File, header: Header, capacity: usize) -> Self {
+        BufferPool {
+            file,
+            header,
+            frames: Vec::new(),
+            page_table: HashMap::new(),
+            hand: 0,
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn read_raw(file: &mut File, id: PageId) -> Result<Vec<u8>, PagedError> {
+        let mut buf = vec![0u8; PAGE_SIZE];
+        file.seek(SeekFrom::Start(id * PAGE_SIZE as u64))?;
+        file.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn write_raw(file: &mut File, id: PageId, buf: &[u8]) -> Result<(), PagedError> {
+        file.seek(SeekFrom::Start(id * PAGE_SIZE as u64))?;
+        file.write_all(buf)?;
+        Ok(())
+    }
+
+    /// Index of the frame holding `id`, reading the page from disk first if
+    /// it is not cached.
+    fn frame(&mut self, id: PageId) -> Result<usize, PagedError> {
+        if let Some(&idx) = self.page_table.get(&id) {
+            self.frames[idx].referenced = true;
+            return Ok(idx);
+        }
+        let page = Page::decode(id, &Self::read_raw(&mut self.file, id)?)?;
+        self.install(id, page, false)
+    }
+
+    fn install(&mut self, id: PageId, page: Page<K, V>, dirty: bool) -> Result<usize, PagedError> {
+        let frame = Frame {
+            id,
+            page,
+            dirty,
+            referenced: true,
+        };
+        if self.frames.len() < self.capacity {
+            self.frames.push(frame);
+            self.page_table.insert(id, self.frames.len() - 1);
+            return Ok(self.frames.len() - 1);
+        }
+
+        while self.frames[self.hand].referenced {
+            self.frames[self.hand].referenced = false;
+            self.hand = (self.hand + 1) % self.capacity;
+        }
+        let idx = self.hand;
+        self.hand = (self.hand + 1) % self.capacity;
+
+        // The victim stays cached until it is safely written back.
+        let victim = &self.frames[idx];
+        if victim.dirty {
+            Self::write_raw(&mut self.file, victim.id, &victim.page.encode()?)?;
+        }
+        let victim = std::mem::replace(&mut self.frames[idx], frame);
+        self.page_table.remove(&victim.id);
+        self.page_table.insert(id, idx);
+        Ok(idx)
+    }
+
+    pub fn get(&mut self, id: PageId) -> Result<&Page<K, V>, PagedError> {
+        let idx = self.frame(id)?;
+        Ok(&self.frames[idx].page)
+    }
+
+    pub fn get_mut(&mut self, id: PageId) -> Result<&mut Page<K, V>, PagedError> {
+        let idx = self.frame(id)?;
+        self.frames[idx].dirty = true;
+        Ok(&mut self.frames[idx].page)
+    }
+
+    pub fn leaf_mut(&mut self, id: PageId) -> Result<&mut LeafPage<K, V>, PagedError> {
+        self.get_mut(id)?.leaf_mut(id)
+    }
+
+    pub fn intermediate_mut(&mut self, id: PageId) -> Result<&mut IntermediatePage<K>, PagedError> {
+        self.get_mut(id)?.intermediate_mut(id)
+    }
+
+    /// Replaces the contents of `id` without reading it from disk.
+    fn put(&mut self, id: PageId, page: Page<K, V>) -> Result<(), PagedError> {
+        match self.page_table.get(&id) {
+            Some(&idx) => {
+                let frame = &mut self.frames[idx];
+                frame.page = page;
+                frame.dirty = true;
+                frame.referenced = true;
+            }
+            None => {
+                self.install(id, page, true)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Stores `page` in a free page, reusing freed ones first.
+    pub fn allocate(&mut self, page: Page<K, V>) -> Result<PageId, PagedError> {
+        let id = match self.header.free_head {
+            Some(id) => {
+                self.header.free_head = match self.get(id)? {
+                    Page::Free(next) => *next,
+                    _ => return Err(PagedError::Corrupted(id)),
+                };
+                id
+            }
+            None => {
+                self.header.page_count += 1;
+                self.header.page_count - 1
+            }
+        };
+        self.put(id, page)?;
+        Ok(id)
+    }
+
+    pub fn free(&mut self, id: PageId) -> Result<(), PagedError> {
+        self.put(id, Page::Free(self.header.free_head))?;
+        self.header.free_head = Some(id);
+        Ok(())
+    }
+
+    /// Writes every dirty page and the header, then syncs the file.
+    pub fn flush(&mut self) -> Result<(), PagedError> {
+        for frame in self.frames.iter_mut().filter(|frame| frame.dirty) {
+            Self::write_raw(&mut self.file, frame.id, &frame.page.encode()?)?;
+            frame.dirty = false;
+        }
+        Self::write_raw(&mut self.file, 0, &self.header.encode()?)?;
+        self.file.sync_all()?;
+        Ok(())
+    }
+}
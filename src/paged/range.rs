@@ -0,0 +1,151 @@
+use super::*;
+use std::collections::VecDeque;
+
+/// Iterator over a key range of a `PagedTree`, following the sibling links
+/// between leaves and reading one leaf at a time from either end.
+pub struct PagedRange<'a, K, V>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    tree: &'a PagedTree<K, V>,
+    bounds: (Bound<K>, Bound<K>),
+    front: VecDeque<(K, V)>,
+    back: VecDeque<(K, V)>,
+    /// Next leaf to read from the front and from the back; both become
+    /// `None` once the two ends have met.
+    lo: Option<PageId>,
+    hi: Option<PageId>,
+}
+
+impl<'a, K, V> PagedRange<'a, K, V>
+where
+    K: Serialize + DeserializeOwned + Clone + Ord,
+    V: Serialize + DeserializeOwned + Clone,
+{
+    pub(super) fn new(
+        tree: &'a PagedTree<K, V>,
+        start: Bound<K>,
+        end: Bound<K>,
+    ) -> Result<Self, PagedError> {
+        let is_empty = match (&start, &end) {
+            (Bound::Included(start), Bound::Included(end)) => start > end,
+            (Bound::Included(start), Bound::Excluded(end))
+            | (Bound::Excluded(start), Bound::Included(end))
+            | (Bound::Excluded(start), Bound::Excluded(end)) => start >= end,
+            _ => false,
+        };
+
+        let (lo, hi) = if is_empty {
+            (None, None)
+        } else {
+            let pool = &mut *tree.pool.borrow_mut();
+            match PagedTree::lower_leaf(pool, start.as_ref())? {
+                Some(lo) => (Some(lo), PagedTree::upper_leaf(pool, end.as_ref())?),
+                None => (None, None),
+            }
+        };
+
+        Ok(PagedRange {
+            tree,
+            bounds: (start, end),
+            front: VecDeque::new(),
+            back: VecDeque::new(),
+            lo,
+            hi,
+        })
+    }
+
+    /// Reads the entries of leaf `id` within the bounds, along with its
+    /// previous and next sibling.
+    #[allow(clippy::type_complexity)]
+    fn load(
+        &self,
+        id: PageId,
+    ) -> Result<(Vec<(K, V)>, Option<PageId>, Option<PageId>), PagedError> {
+        let mut pool = self.tree.pool.borrow_mut();
+        let leaf = pool.get(id)?.leaf(id)?;
+        let kv_series = leaf
+            .kv_series()
+            .iter()
+            .filter(|(key, _)| self.bounds.contains(key))
+            .cloned()
+            .collect();
+        Ok((kv_series, leaf.prev, leaf.next))
+    }
+
+    /// Marks leaf `id` as read and tells whether the two ends have met.
+    fn meet(&mut self, id: PageId) -> bool {
+        if self.lo == Some(id) && self.hi == Some(id) {
+            self.lo = None;
+            self.hi = None;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl<K, V> Iterator for PagedRange<'_, K, V>
+where
+    K: Serialize + DeserializeOwned + Clone + Ord,
+    V: Serialize + DeserializeOwned + Clone,
+{
+    type Item = Result<(K, V), PagedError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(pair) = self.front.pop_front() {
+                return Some(Ok(pair));
+            }
+            let id = match self.lo {
+                Some(id) => id,
+                None => return self.back.pop_front().map(Ok),
+            };
+            match self.load(id) {
+                Ok((kv_series, _, next)) => {
+                    self.front.extend(kv_series);
+                    if !self.meet(id) {
+                        self.lo = next;
+                    }
+                }
+                Err(e) => {
+                    self.lo = None;
+                    self.hi = None;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+impl<K, V> DoubleEndedIterator for PagedRange<'_, K, V>
+where
+    K: Serialize + DeserializeOwned + Clone + Ord,
+    V: Serialize + DeserializeOwned + Clone,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(pair) = self.back.pop_back() {
+                return Some(Ok(pair));
+            }
+            let id = match self.hi {
+                Some(id) => id,
+                None => return self.front.pop_back().map(Ok),
+            };
+            match self.load(id) {
+                Ok((kv_series, prev, _)) => {
+                    self.back.extend(kv_series);
+                    if !self.meet(id) {
+                        self.hi = prev;
+                    }
+                }
+                Err(e) => {
+                    self.lo = None;
+                    self.hi = None;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
@@ -1,61 +1,281 @@
-use super::io;
-use super::node::{Node, RootNode};
+use super::io::{self, Format};
+use super::node::{Comparator, Entry, Fanout, Natural, NodeError, RootNode};
 use super::transaction::Write;
-use serde::{de::DeserializeOwned, Serialize};
+use super::wal::{self, TornTail, Wal};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{collections::HashMap, error::Error, fmt, fs, hash::Hash, path::Path};
 
+#[derive(thiserror::Error, Debug)]
+pub enum PersistenceError {
+    #[error("dump was written with collation {found:?}, not {expected:?}")]
+    CollationMismatch { expected: String, found: String },
+    #[error("log records right after the checkpoint at record {0} are missing")]
+    MissingRecords(u64),
+}
+
+fn natural() -> String {
+    "natural".to_string()
+}
+
+/// Tree loaded by `recover`, along with what recovery found in the log.
+pub struct Recovery<K, V, C = Natural>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    pub root_node: RootNode<K, V, C>,
+    /// Sequence number of the last log record the dump held.
+    pub checkpoint: u64,
+    /// Number of log records skipped since the dump already held them.
+    pub skipped: usize,
+    /// Record cut short at the end of the log, left out of the tree.
+    pub torn_tail: Option<TornTail>,
+}
+
+/// Contents of the dump file.
+#[derive(Serialize, Deserialize)]
+struct Tree<K, V> {
+    fanout: Fanout,
+    /// Name of the comparator the keys are ordered by.
+    #[serde(default = "natural")]
+    collation: String,
+    kv_series: Vec<(K, V)>,
+    /// Sequence number of the last log record the dump holds.
+    #[serde(default)]
+    checkpoint: u64,
+}
+
+/// Shapes a JSON dump file may have, including those of older versions.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonDump<K, V> {
+    Tree(Tree<K, V>),
+    /// Dumps written before the fanout was recorded, loaded with the default.
+    Entries(Vec<(K, V)>),
+}
+
 pub fn dump<
     K: 'static + fmt::Debug + Clone + Serialize + Ord,
     V: 'static + fmt::Debug + Clone + Serialize,
-    const N: usize,
+    C: Comparator<K> + Clone,
 >(
-    root_node: &RootNode<K, V, N>,
+    root_node: &RootNode<K, V, C>,
+    wal: &mut Wal,
+) -> Result<(), Box<dyn Error>> {
+    dump_as(root_node, wal, Format::default())
+}
+
+/// Dumps the tree with its payload encoded in `format` into the folder of
+/// `wal`, and removes the log segments it makes redundant. The tree has to
+/// hold every record written to `wal` so far.
+pub fn dump_as<
+    K: 'static + fmt::Debug + Clone + Serialize + Ord,
+    V: 'static + fmt::Debug + Clone + Serialize,
+    C: Comparator<K> + Clone,
+>(
+    root_node: &RootNode<K, V, C>,
+    wal: &mut Wal,
+    format: Format,
+) -> Result<(), Box<dyn Error>> {
+    let (lsn, token) = wal.begin_checkpoint()?;
+    checkpoint(root_node, wal.folder_path(), lsn, format)?;
+    wal.finish_checkpoint(token)
+}
+
+/// Replaces the dump in `folder_path` by one of a tree holding every log
+/// record up to `lsn` and no later one. A crash before this returns leaves the
+/// old dump in place.
+///
+/// This is the middle step of a checkpoint taken while the table keeps
+/// committing: take `lsn` from `Wal::begin_checkpoint` together with a clone
+/// of the tree, which shares its nodes, write the clone here without holding
+/// on to the log, and hand the token to `Wal::finish_checkpoint` afterwards.
+/// Only one checkpoint should be in progress at a time.
+pub fn checkpoint<
+    K: 'static + fmt::Debug + Clone + Serialize + Ord,
+    V: 'static + fmt::Debug + Clone + Serialize,
+    C: Comparator<K> + Clone,
+>(
+    root_node: &RootNode<K, V, C>,
     folder_path: &Path,
+    lsn: u64,
+    format: Format,
 ) -> Result<(), Box<dyn Error>> {
-    let file_path = io::dump(folder_path, &Node::collect(root_node))?;
-    fs::rename(file_path, &folder_path.join(super::DUMP_FILE_PATH))?;
-    io::remove_dir(&folder_path.join(super::WAL_FOLDER_PATH))
+    let tree = Tree {
+        fanout: root_node.fanout(),
+        collation: root_node.collation().name().to_string(),
+        kv_series: root_node.iter().collect::<Vec<_>>(),
+        checkpoint: lsn,
+    };
+    let file_path = io::dump(folder_path, &tree, format)?;
+    fs::rename(file_path, folder_path.join(super::DUMP_FILE_PATH))?;
+    remove_file(&folder_path.join(super::LEGACY_DUMP_FILE_PATH))?;
+    io::sync_dir(folder_path)
+}
+
+fn remove_file(file_path: &Path) -> Result<(), Box<dyn Error>> {
+    match fs::remove_file(file_path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(Box::new(e)),
+        _ => Ok(()),
+    }
+}
+
+/// Reads the dump file, falling back to the one older versions wrote.
+fn read_dump<K, V>(folder_path: &Path) -> Result<Tree<K, V>, Box<dyn Error>>
+where
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+{
+    let (format, payload) = match io::read(&folder_path.join(super::DUMP_FILE_PATH)) {
+        Err(e)
+            if matches!(e.downcast_ref::<std::io::Error>(),
+                Some(e) if e.kind() == std::io::ErrorKind::NotFound) =>
+        {
+            io::read(&folder_path.join(super::LEGACY_DUMP_FILE_PATH))?
+        }
+        result => result?,
+    };
+    Ok(match format {
+        Format::Binary => format.decode(&payload)?,
+        Format::Json => match format.decode(&payload)? {
+            JsonDump::Tree(tree) => tree,
+            JsonDump::Entries(kv_series) => Tree {
+                fanout: Fanout::default(),
+                collation: natural(),
+                kv_series,
+                checkpoint: 0,
+            },
+        },
+    })
 }
 
 pub fn load<
     K: 'static + fmt::Debug + Clone + Serialize + DeserializeOwned + Ord + Hash,
     V: 'static + fmt::Debug + Clone + Serialize + DeserializeOwned,
-    const N: usize,
 >(
     folder_path: &Path,
-) -> Result<RootNode<K, V, N>, Box<dyn Error>> {
-    let mut root_node = RootNode::<K, V, N>::new();
-    let kv_series: Vec<(K, V)> = io::load(&folder_path.join(super::DUMP_FILE_PATH))?;
+) -> Result<RootNode<K, V>, Box<dyn Error>> {
+    load_collated(folder_path, Natural)
+}
 
-    for (key, value) in kv_series.into_iter() {
-        root_node.insert(&key, value)?;
+/// Loads a tree whose keys are ordered by `collation`. The dump has to have
+/// been written by a tree with a comparator of the same name.
+pub fn load_collated<
+    K: 'static + fmt::Debug + Clone + Serialize + DeserializeOwned + Ord + Hash,
+    V: 'static + fmt::Debug + Clone + Serialize + DeserializeOwned,
+    C: Comparator<K> + Clone,
+>(
+    folder_path: &Path,
+    collation: C,
+) -> Result<RootNode<K, V, C>, Box<dyn Error>> {
+    recover(folder_path, collation).map(|recovery| recovery.root_node)
+}
+
+/// Loads a tree like `load_collated`, and also reports the torn tail of the
+/// log left behind by a crash in the middle of a commit, if any. Its record
+/// is left out; `Wal::open` cuts it off for good. Any other damage to the log
+/// fails the recovery.
+///
+/// Records up to the checkpoint of the dump are skipped, and the rest are
+/// applied so that a write already in the tree leaves it as it is. Replaying
+/// the same log over the same dump thus always ends in the same tree, even
+/// when a crash left behind segments the dump already holds.
+pub fn recover<
+    K: 'static + fmt::Debug + Clone + Serialize + DeserializeOwned + Ord + Hash,
+    V: 'static + fmt::Debug + Clone + Serialize + DeserializeOwned,
+    C: Comparator<K> + Clone,
+>(
+    folder_path: &Path,
+    collation: C,
+) -> Result<Recovery<K, V, C>, Box<dyn Error>> {
+    let Tree {
+        fanout,
+        collation: found,
+        kv_series,
+        checkpoint,
+    } = read_dump(folder_path)?;
+    if found != collation.name() {
+        return Err(Box::new(PersistenceError::CollationMismatch {
+            expected: collation.name().to_string(),
+            found,
+        }));
     }
+    let mut recovery = Recovery {
+        root_node: RootNode::from_sorted_iter_with(fanout, collation, kv_series)?,
+        checkpoint,
+        skipped: 0,
+        torn_tail: None,
+    };
 
-    match fs::read_dir(&folder_path.join(super::WAL_FOLDER_PATH)) {
-        Ok(dir) => {
-            let mut entries = dir
-                .map(|res| res.map(|e| e.path()))
-                .collect::<Result<Vec<_>, std::io::Error>>()?;
-            entries.sort();
-
-            for path in entries {
-                let write_set: HashMap<K, Write<V>> = io::load(&path)?;
-                for (key, w) in write_set {
-                    match w {
-                        Write::Insert(value) => root_node.insert(&key, value),
-                        Write::Update(value) => root_node.update(&key, value),
-                        Write::Remove => root_node.remove(&key),
-                    }?;
-                }
-            }
-            Ok(root_node)
+    // The segment following the checkpoint has to be there, even if it holds
+    // no record yet.
+    let wal_path = folder_path.join(super::WAL_FOLDER_PATH);
+    let first_lsn = if wal_path.exists() {
+        wal::segments(&wal_path)?
+            .first()
+            .map(|(first_lsn, _)| *first_lsn)
+    } else {
+        None
+    };
+    if first_lsn.map_or(checkpoint > 0, |first_lsn| first_lsn > checkpoint + 1) {
+        return Err(Box::new(PersistenceError::MissingRecords(checkpoint)));
+    }
+    if !wal_path.exists() {
+        return Ok(recovery);
+    }
+
+    // Files of one commit each, as written before the log was segmented,
+    // come before any segment. Any checkpoint was taken after all of them.
+    let (write_sets, legacy_tail) = wal::legacy_records(&wal_path)?;
+    for write_set in write_sets {
+        if checkpoint > 0 {
+            recovery.skipped += 1;
+        } else {
+            replay(&mut recovery.root_node, write_set)?;
+        }
+    }
+    let (records, torn_tail) = wal::records(&wal_path)?;
+    for record in records {
+        if record.lsn <= checkpoint {
+            recovery.skipped += 1;
+        } else {
+            replay(
+                &mut recovery.root_node,
+                record.format.decode(&record.payload)?,
+            )?;
         }
-        Err(e) => {
-            if let std::io::ErrorKind::NotFound = e.kind() {
-                Ok(root_node)
-            } else {
-                Err(Box::new(e))
-            }
+    }
+    recovery.torn_tail = torn_tail.or(legacy_tail);
+    Ok(recovery)
+}
+
+/// Applies a write set so that applying it twice has the same effect as
+/// applying it once: inserts and updates overwrite whatever the key holds,
+/// and removing a missing key does nothing.
+fn replay<K, V, C>(
+    root_node: &mut RootNode<K, V, C>,
+    write_set: HashMap<K, Write<V>>,
+) -> Result<(), Box<dyn Error>>
+where
+    K: 'static + fmt::Debug + Clone + Ord + Hash,
+    V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone,
+{
+    for (key, w) in write_set {
+        match w {
+            Write::Insert(value) | Write::Update(value) => match root_node.entry(key) {
+                Entry::Occupied(mut entry) => {
+                    entry.insert(value);
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+            },
+            Write::Remove => match root_node.remove(&key) {
+                Err(NodeError::NotFound) => {}
+                result => result?,
+            },
         }
     }
+    Ok(())
 }
@@ -1,7 +1,10 @@
 mod primitive;
 mod secondary;
+mod snapshot;
+#[allow(clippy::module_inception)]
 mod table;
 
 pub use primitive::Primitive;
 pub use secondary::{DefaultSecondaryIndex, SecondaryIndex};
+pub use snapshot::TableSnapshot;
 pub use table::Table;
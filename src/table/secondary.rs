@@ -1,6 +1,6 @@
 use super::primitive::Primitive;
-use crate::{Node, RootNode};
-use std::{cmp, collections::HashSet, error::Error, fmt, hash::Hash, marker};
+use crate::{node::NodeError, Comparator, Fanout, MultiMap, Natural};
+use std::{cmp, error::Error, fmt, marker, sync::Arc};
 
 #[derive(thiserror::Error, Debug)]
 pub enum SecondaryIndexError {
@@ -8,61 +8,94 @@ pub enum SecondaryIndexError {
     IllegalKeyType,
 }
 
-pub trait SecondaryIndex<K, V, const N: usize>
+/// Index from a part of each row's value to the primary keys of the rows.
+/// Indexes are shared with the threads reading a `TableSnapshot`, so they
+/// have to be `Send + Sync`.
+pub trait SecondaryIndex<K, V>: Send + Sync
 where
     K: fmt::Debug,
 {
-    fn find(&self, key: &Primitive) -> Option<&HashSet<K>>;
+    fn find<'a>(&'a self, key: &Primitive) -> Box<dyn Iterator<Item = &'a K> + 'a>;
     fn select(&self, value: V) -> Primitive;
     fn validate(&self, value: &Primitive) -> bool;
     fn append_to(&mut self, key: &Primitive, primary_key: K) -> Result<(), Box<dyn Error>>;
     fn remove_from(&mut self, key: &Primitive, primary_key: K) -> Result<(), Box<dyn Error>>;
+    /// Returns a copy of the index as it is now, sharing its nodes with the
+    /// original until either is written to.
+    fn snapshot(&self) -> Box<dyn SecondaryIndex<K, V>>;
 }
 
-pub struct DefaultSecondaryIndex<K1, V, K2, FnSelector, FnValidator, const N: usize>
+pub struct DefaultSecondaryIndex<K1, V, K2, FnSelector, FnValidator, C = Natural>
 where
     K1: fmt::Debug,
     K2: fmt::Debug,
-    FnSelector: 'static + Fn(V) -> Primitive,
-    FnValidator: 'static + Fn(&Primitive) -> Option<&K2>,
+    FnSelector: 'static + Send + Sync + Fn(V) -> Primitive,
+    FnValidator: 'static + Send + Sync + Fn(&Primitive) -> Option<&K2>,
 {
-    index: RootNode<K2, HashSet<K1>, N>,
-    selector: FnSelector,
-    validator: FnValidator,
-    phantom: marker::PhantomData<V>,
+    index: MultiMap<K2, K1, C>,
+    selector: Arc<FnSelector>,
+    validator: Arc<FnValidator>,
+    /// Values are only passed in, so they need not be `Send` or `Sync`.
+    phantom: marker::PhantomData<fn(V)>,
 }
 
-impl<K1, V, K2, FnSelector, FnValidator, const N: usize>
-    DefaultSecondaryIndex<K1, V, K2, FnSelector, FnValidator, N>
+impl<K1, V, K2, FnSelector, FnValidator> DefaultSecondaryIndex<K1, V, K2, FnSelector, FnValidator>
 where
-    K1: 'static + fmt::Debug + Clone,
+    K1: 'static + fmt::Debug + Clone + cmp::Ord,
     K2: 'static + fmt::Debug + Clone + cmp::Ord,
-    FnSelector: 'static + Fn(V) -> Primitive,
-    FnValidator: 'static + Fn(&Primitive) -> Option<&K2>,
+    FnSelector: 'static + Send + Sync + Fn(V) -> Primitive,
+    FnValidator: 'static + Send + Sync + Fn(&Primitive) -> Option<&K2>,
 {
     pub fn new(selector: FnSelector, validator: FnValidator) -> Self {
+        Self::with_fanout(selector, validator, Fanout::default())
+    }
+
+    pub fn with_fanout(selector: FnSelector, validator: FnValidator, fanout: Fanout) -> Self {
+        Self::with_collation(selector, validator, fanout, Natural)
+    }
+}
+
+impl<K1, V, K2, FnSelector, FnValidator, C>
+    DefaultSecondaryIndex<K1, V, K2, FnSelector, FnValidator, C>
+where
+    K1: 'static + fmt::Debug + Clone + cmp::Ord,
+    K2: 'static + fmt::Debug + Clone + cmp::Ord,
+    FnSelector: 'static + Send + Sync + Fn(V) -> Primitive,
+    FnValidator: 'static + Send + Sync + Fn(&Primitive) -> Option<&K2>,
+    C: Comparator<K2> + Clone,
+{
+    /// Creates an index ordering its keys by `collation`, so that lookups
+    /// find every row whose key is equal to the given one under it.
+    pub fn with_collation(
+        selector: FnSelector,
+        validator: FnValidator,
+        fanout: Fanout,
+        collation: C,
+    ) -> Self {
         Self {
-            index: RootNode::new(),
-            selector,
-            validator,
-            phantom: marker::PhantomData::<V>,
+            index: MultiMap::with_collation(fanout, collation),
+            selector: Arc::new(selector),
+            validator: Arc::new(validator),
+            phantom: marker::PhantomData,
         }
     }
 }
 
-impl<K1, V, K2, FnSelector, FnValidator, const N: usize> SecondaryIndex<K1, V, N>
-    for DefaultSecondaryIndex<K1, V, K2, FnSelector, FnValidator, N>
+impl<K1, V, K2, FnSelector, FnValidator, C> SecondaryIndex<K1, V>
+    for DefaultSecondaryIndex<K1, V, K2, FnSelector, FnValidator, C>
 where
-    K1: 'static + fmt::Debug + Clone + cmp::Eq + Hash,
-    K2: 'static + fmt::Debug + Clone + cmp::Ord,
-    FnSelector: 'static + Fn(V) -> Primitive,
-    FnValidator: 'static + Fn(&Primitive) -> Option<&K2>,
+    K1: 'static + fmt::Debug + Clone + cmp::Ord + Send + Sync,
+    K2: 'static + fmt::Debug + Clone + cmp::Ord + Send + Sync,
+    FnSelector: 'static + Send + Sync + Fn(V) -> Primitive,
+    FnValidator: 'static + Send + Sync + Fn(&Primitive) -> Option<&K2>,
+    V: 'static,
+    C: 'static + Comparator<K2> + Clone,
 {
-    fn find(&self, key: &Primitive) -> Option<&HashSet<K1>> {
+    fn find<'a>(&'a self, key: &Primitive) -> Box<dyn Iterator<Item = &'a K1> + 'a> {
         if let Some(key) = (self.validator)(key) {
-            self.index.find(key)
+            Box::new(self.index.find_all(key))
         } else {
-            None
+            Box::new(std::iter::empty())
         }
     }
 
@@ -71,30 +104,15 @@ where
     }
 
     fn validate(&self, value: &Primitive) -> bool {
-        match (self.validator)(value) {
-            Some(_) => true,
-            None => false,
-        }
+        (self.validator)(value).is_some()
     }
 
     fn append_to(&mut self, key: &Primitive, primary_key: K1) -> Result<(), Box<dyn Error>> {
         if let Some(key) = (self.validator)(key) {
-            match self.index.find(key) {
-                Some(primary_keys) => {
-                    let pointer = primary_keys as *const HashSet<K1>;
-                    let address = pointer as usize;
-                    let pointer = address as *mut HashSet<K1>;
-                    unsafe {
-                        (*pointer).insert(primary_key);
-                    }
-                }
-                None => self.index.insert(key, {
-                    let mut primary_keys = HashSet::new();
-                    primary_keys.insert(primary_key);
-                    primary_keys
-                })?,
+            match self.index.insert(key.clone(), primary_key) {
+                Ok(()) | Err(NodeError::Duplicated) => Ok(()),
+                Err(e) => Err(Box::new(e)),
             }
-            Ok(())
         } else {
             Err(Box::new(SecondaryIndexError::IllegalKeyType))
         }
@@ -102,21 +120,21 @@ where
 
     fn remove_from(&mut self, key: &Primitive, primary_key: K1) -> Result<(), Box<dyn Error>> {
         if let Some(key) = (self.validator)(key) {
-            if let Some(primary_keys) = self.index.find(key) {
-                if primary_keys.len() == 1 {
-                    self.index.remove(key)?;
-                } else {
-                    let pointer = primary_keys as *const HashSet<K1>;
-                    let address = pointer as usize;
-                    let pointer = address as *mut HashSet<K1>;
-                    unsafe {
-                        (*pointer).remove(&primary_key);
-                    }
-                }
+            match self.index.remove_one(key, &primary_key) {
+                Ok(()) | Err(NodeError::NotFound) => Ok(()),
+                Err(e) => Err(Box::new(e)),
             }
-            Ok(())
         } else {
             Err(Box::new(SecondaryIndexError::IllegalKeyType))
         }
     }
+
+    fn snapshot(&self) -> Box<dyn SecondaryIndex<K1, V>> {
+        Box::new(Self {
+            index: self.index.clone(),
+            selector: Arc::clone(&self.selector),
+            validator: Arc::clone(&self.validator),
+            phantom: marker::PhantomData,
+        })
+    }
 }
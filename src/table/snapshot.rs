@@ -0,0 +1,95 @@
+use super::{Primitive, SecondaryIndex};
+use crate::{
+    cursor::Paginate, transaction::TransactionError, Comparator, Natural, Node, Range, Snapshot,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt,
+    hash::Hash,
+    ops::{Bound, RangeBounds},
+};
+
+/// Read-only view of a `Table` at the time `Table::snapshot` was called,
+/// covering the primary and every secondary index. Commits to the table
+/// after that do not affect it, and reading it does not hold on to the
+/// table, so it can be handed to other threads while transactions go on.
+pub struct TableSnapshot<K, V, C = Natural>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    primary: Snapshot<K, V, C>,
+    secondaries: HashMap<String, Box<dyn SecondaryIndex<K, V>>>,
+}
+
+impl<K, V, C> TableSnapshot<K, V, C>
+where
+    K: 'static + fmt::Debug + Clone + Ord,
+    V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone,
+{
+    pub(super) fn new(
+        primary: Snapshot<K, V, C>,
+        secondaries: HashMap<String, Box<dyn SecondaryIndex<K, V>>>,
+    ) -> Self {
+        TableSnapshot {
+            primary,
+            secondaries,
+        }
+    }
+
+    pub fn find(&self, key: &K) -> Option<&V> {
+        self.primary.find(key)
+    }
+
+    /// Rows whose key is within `range`, in key order.
+    pub fn range<R>(&self, range: R) -> Range<'_, K, V>
+    where
+        R: RangeBounds<K>,
+    {
+        self.primary.range(range)
+    }
+
+    /// Number of rows.
+    pub fn len(&self) -> usize {
+        self.primary.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.primary.is_empty()
+    }
+
+    /// Primary keys of the rows whose value has `key` in secondary `index`.
+    pub fn select(&self, index: &String, key: &Primitive) -> Result<HashSet<K>, Box<dyn Error>>
+    where
+        K: Hash,
+    {
+        let index = self
+            .secondaries
+            .get(index)
+            .ok_or(TransactionError::SecondaryIndexNotFound)?;
+
+        if !index.validate(key) {
+            Err(TransactionError::IllegalKeyType)?;
+        }
+
+        Ok(index.find(key).cloned().collect())
+    }
+}
+
+impl<K, V, C> Paginate<K, V> for TableSnapshot<K, V, C>
+where
+    K: 'static + fmt::Debug + Clone + Ord,
+    V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone,
+{
+    fn page_within(
+        &self,
+        bounds: (Bound<&K>, Bound<&K>),
+        size: usize,
+        reverse: bool,
+    ) -> Vec<(K, V)> {
+        (*self.primary).page_within(bounds, size, reverse)
+    }
+}
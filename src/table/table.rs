@@ -1,12 +1,40 @@
-use super::secondary::SecondaryIndex;
-use crate::RootNode;
+use super::{SecondaryIndex, TableSnapshot};
+use crate::{Comparator, Natural, RootNode};
 use std::{collections::HashMap, fmt};
 
-pub struct Table<K, V, const N: usize>
+/// Rows keyed by `K`, along with secondary indexes over their values.
+///
+/// The primary index orders keys by `C`, and transactions merge their
+/// pending writes into range scans by the same comparator. Pending writes
+/// are kept in a hash map though, so keys that `C` considers equal have to
+/// be equal by `Hash` and `Eq` as well for a transaction to see the same
+/// keys the tree does.
+pub struct Table<K, V, C = Natural>
 where
     K: fmt::Debug,
     V: fmt::Debug,
 {
-    pub primary: RootNode<K, V, N>,
-    pub secondaries: HashMap<String, Box<dyn SecondaryIndex<K, V, N>>>,
+    pub primary: RootNode<K, V, C>,
+    pub secondaries: HashMap<String, Box<dyn SecondaryIndex<K, V>>>,
+}
+
+impl<K, V, C> Table<K, V, C>
+where
+    K: 'static + fmt::Debug + Clone + Ord,
+    V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone,
+{
+    /// Returns a view of the table as it is now, which later commits do not
+    /// affect. As with `RootNode::snapshot`, taking it only bumps reference
+    /// counts, and nodes of any index are copied when a write reaches one
+    /// that is still shared.
+    pub fn snapshot(&self) -> TableSnapshot<K, V, C> {
+        TableSnapshot::new(
+            self.primary.snapshot(),
+            self.secondaries
+                .iter()
+                .map(|(name, index)| (name.clone(), index.snapshot()))
+                .collect(),
+        )
+    }
 }
@@ -0,0 +1,44 @@
+#[test]
+fn concurrent_stress() -> Result<(), Box<dyn std::error::Error>> {
+    const THREADS: u64 = 4;
+    const COUNT: u64 = 5000;
+
+    let tree = crate::ConcurrentTree::<u64, u64>::with_fanout(crate::Fanout::new(8, 9));
+    std::thread::scope(|scope| {
+        for thread in 0..THREADS {
+            let tree = &tree;
+            scope.spawn(move || {
+                for i in 0..COUNT {
+                    let key = (i * 48271 + 11) % COUNT * THREADS + thread;
+                    tree.insert(&key, key).unwrap();
+                    assert_eq!(tree.find(&key), Some(key));
+                    match key % 3 {
+                        0 => tree.remove(&key).unwrap(),
+                        1 => tree.update(&key, key * 2).unwrap(),
+                        _ => {}
+                    }
+                }
+            });
+            scope.spawn(move || {
+                for key in 0..COUNT * THREADS {
+                    if let Some(value) = tree.find(&key) {
+                        assert!(value == key || value == key * 2);
+                    }
+                }
+            });
+        }
+    });
+
+    let mut index = crate::RootNode::<u64, u64>::with_fanout(crate::Fanout::new(8, 9));
+    for key in (0..COUNT * THREADS).filter(|key| key % 3 != 0) {
+        let value = if key % 3 == 1 { key * 2 } else { key };
+        index.insert(&key, value)?;
+    }
+    assert_eq!(tree.len(), index.len());
+    assert_eq!(tree.collect(), crate::Node::collect(&index));
+    assert!(matches!(
+        tree.insert(&1, 0),
+        Err(crate::node::NodeError::Duplicated)
+    ));
+    Ok(())
+}
@@ -0,0 +1,83 @@
+#[test]
+fn cursor_pages() -> Result<(), Box<dyn std::error::Error>> {
+    let mut index = crate::RootNode::<usize, usize>::with_fanout(crate::Fanout::new(4, 5));
+    for i in 0..50 {
+        index.insert(&(i * 2), i)?;
+    }
+
+    let page = crate::Cursor::start().next_page(&index, 10);
+    let keys: Vec<_> = page.entries.iter().map(|(key, _)| *key).collect();
+    assert_eq!(keys, (0..20).step_by(2).collect::<Vec<_>>());
+
+    // Writes between requests neither skip nor repeat entries.
+    let token = page.end.to_token()?;
+    index.insert(&17, 0)?;
+    index.insert(&19, 0)?;
+    index.remove(&18)?;
+    let cursor = crate::Cursor::<usize>::from_token(&token)?;
+    let page = cursor.next_page(&index, 3);
+    let keys: Vec<_> = page.entries.iter().map(|(key, _)| *key).collect();
+    assert_eq!(keys, vec![19, 20, 22]);
+
+    let page = page.start.prev_page(&index, 4);
+    let keys: Vec<_> = page.entries.iter().map(|(key, _)| *key).collect();
+    assert_eq!(keys, vec![12, 14, 16, 17]);
+
+    let page = crate::Cursor::seek(90).next_page(&index, 10);
+    assert_eq!(page.entries.len(), 5);
+    assert!(page.end.next_page(&index, 10).entries.is_empty());
+    assert_eq!(
+        crate::Cursor::end().prev_page(&index, 2).entries,
+        vec![(96, 48), (98, 49)]
+    );
+    assert!(crate::Cursor::<usize>::from_token("not a token").is_err());
+    Ok(())
+}
+
+#[test]
+fn cursor_pages_transaction() -> Result<(), Box<dyn std::error::Error>> {
+    let mut table = super::table(crate::RootNode::<usize, usize>::with_fanout(
+        crate::Fanout::new(4, 5),
+    ));
+    for i in 0..20 {
+        table.primary.insert(&i, i)?;
+    }
+
+    let mut transaction = crate::Transaction::new(&mut table);
+    transaction.exec(crate::Request::Remove(3))?;
+    transaction.exec(crate::Request::Insert((30, 30)))?;
+    transaction.exec(crate::Request::Update((4, 40)))?;
+
+    let page = crate::Cursor::seek(2).next_page(&transaction, 3);
+    assert_eq!(page.entries, vec![(2, 2), (4, 40), (5, 5)]);
+    let page = crate::Cursor::end().prev_page(&transaction, 2);
+    assert_eq!(page.entries, vec![(19, 19), (30, 30)]);
+
+    // Paging all the way through in either direction sees every row once.
+    let expected: Vec<(usize, usize)> = (0..20)
+        .filter(|&i| i != 3)
+        .map(|i| (i, if i == 4 { 40 } else { i }))
+        .chain(Some((30, 30)))
+        .collect();
+    let (mut forward, mut cursor) = (Vec::new(), crate::Cursor::start());
+    loop {
+        let page = cursor.next_page(&transaction, 4);
+        if page.entries.is_empty() {
+            break;
+        }
+        forward.extend(page.entries);
+        cursor = page.end;
+    }
+    assert_eq!(forward, expected);
+    let (mut backward, mut cursor) = (Vec::new(), crate::Cursor::end());
+    loop {
+        let page = cursor.prev_page(&transaction, 3);
+        if page.entries.is_empty() {
+            break;
+        }
+        backward.splice(0..0, page.entries);
+        cursor = page.start;
+    }
+    assert_eq!(backward, expected);
+    Ok(())
+}
@@ -1,3 +1,28 @@
+mod concurrent;
+mod cursor;
 mod node;
+mod paged;
 mod secondary;
 mod transaction;
+mod wal;
+
+/// Empty folder for a test to keep a database in, apart from other tests.
+#[cfg(test)]
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("database_{}", name));
+    let _ = std::fs::remove_dir_all(&path);
+    path
+}
+
+/// Table of `primary` without secondary indexes.
+#[cfg(test)]
+fn table<K, V, C>(primary: crate::RootNode<K, V, C>) -> crate::Table<K, V, C>
+where
+    K: std::fmt::Debug,
+    V: std::fmt::Debug,
+{
+    crate::Table {
+        primary,
+        secondaries: std::collections::HashMap::new(),
+    }
+}
@@ -1,37 +1,37 @@
 #[test]
 fn node_insert() -> Result<(), Box<dyn std::error::Error>> {
-    let mut index = crate::RootNode::<&str, &str, 10>::new();
-    crate::Node::insert(&mut index, &"key", "value")?;
+    let mut index = crate::RootNode::<&str, &str>::with_fanout(crate::Fanout::new(10, 11));
+    index.insert(&"key", "value")?;
     assert_eq!(crate::Node::find(&index, &"key"), Some(&"value"));
     Ok(())
 }
 
 #[test]
 fn node_update() -> Result<(), Box<dyn std::error::Error>> {
-    let mut index = crate::RootNode::<&str, &str, 10>::new();
-    crate::Node::insert(&mut index, &"key", "value")?;
-    crate::Node::update(&mut index, &"key", "value_updated")?;
+    let mut index = crate::RootNode::<&str, &str>::with_fanout(crate::Fanout::new(10, 11));
+    index.insert(&"key", "value")?;
+    index.update(&"key", "value_updated")?;
     assert_eq!(crate::Node::find(&index, &"key"), Some(&"value_updated"));
     Ok(())
 }
 
 #[test]
 fn node_remove() -> Result<(), Box<dyn std::error::Error>> {
-    let mut index = crate::RootNode::<&str, &str, 10>::new();
-    crate::Node::insert(&mut index, &"key", "value")?;
-    crate::Node::remove(&mut index, &"key")?;
+    let mut index = crate::RootNode::<&str, &str>::with_fanout(crate::Fanout::new(10, 11));
+    index.insert(&"key", "value")?;
+    index.remove(&"key")?;
     assert_eq!(crate::Node::find(&index, &"key"), None);
     Ok(())
 }
 
 #[test]
 fn node_insert_many() -> Result<(), Box<dyn std::error::Error>> {
-    let mut index = crate::RootNode::<String, String, 10>::new();
+    let mut index = crate::RootNode::<String, String>::with_fanout(crate::Fanout::new(10, 11));
 
     for i in 0..1000 {
         let key = format!("key{}", i);
         let value = format!("value{}", i);
-        crate::Node::insert(&mut index, &key, value.clone())?;
+        index.insert(&key, value.clone())?;
         assert_eq!(crate::Node::find(&index, &key), Some(&value));
     }
 
@@ -40,17 +40,17 @@ fn node_insert_many() -> Result<(), Box<dyn std::error::Error>> {
 
 #[test]
 fn node_remove_many() -> Result<(), Box<dyn std::error::Error>> {
-    let mut index = crate::RootNode::<String, String, 10>::new();
+    let mut index = crate::RootNode::<String, String>::with_fanout(crate::Fanout::new(10, 11));
 
     for i in 0..1000 {
         let key = format!("key{}", i);
         let value = format!("value{}", i);
-        crate::Node::insert(&mut index, &key, value)?;
+        index.insert(&key, value)?;
     }
 
     for i in 0..1000 {
         let key = format!("key{}", i);
-        crate::Node::remove(&mut index, &key)?;
+        index.remove(&key)?;
         assert_eq!(crate::Node::find(&index, &key), None);
     }
 
@@ -64,17 +64,19 @@ fn node_remove_many() -> Result<(), Box<dyn std::error::Error>> {
 
 #[test]
 fn node_dump() -> Result<(), Box<dyn std::error::Error>> {
-    crate::io::remove_dir(std::path::Path::new("./data"))?;
-    let mut index = crate::RootNode::<String, String, 10>::new();
+    let folder_path = super::temp_dir("node_dump");
+    let mut wal = crate::Wal::open(&folder_path)?;
+    let mut index = crate::RootNode::<String, String>::with_fanout(crate::Fanout::new(10, 11));
 
     for i in 0..1000 {
         let key = format!("key{}", i);
         let value = format!("value{}", i);
-        crate::Node::insert(&mut index, &key, value.clone())?;
+        index.insert(&key, value.clone())?;
     }
 
-    crate::dump(&index, std::path::Path::new("./data"))?;
-    index = crate::load(std::path::Path::new("./data"))?;
+    crate::dump(&index, &mut wal)?;
+    index = crate::load(&folder_path)?;
+    assert_eq!(index.fanout(), crate::Fanout::new(10, 11));
 
     for i in 0..1000 {
         let key = format!("key{}", i);
@@ -84,3 +86,639 @@ fn node_dump() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn node_range() -> Result<(), Box<dyn std::error::Error>> {
+    use std::ops::Bound;
+    let mut index = crate::RootNode::<u32, u32>::with_fanout(crate::Fanout::new(10, 11));
+
+    for i in (0..1000).rev() {
+        index.insert(&(i * 2), i)?;
+    }
+
+    let keys = |range: crate::Range<u32, u32>| range.map(|(key, _)| *key).collect::<Vec<_>>();
+    assert_eq!(
+        keys(index.range(..)),
+        (0..1000).map(|i| i * 2).collect::<Vec<_>>()
+    );
+    assert_eq!(keys(index.range(100..110)), vec![100, 102, 104, 106, 108]);
+    assert_eq!(keys(index.range(101..=110)), vec![102, 104, 106, 108, 110]);
+    assert_eq!(
+        keys(index.range(1990..)),
+        vec![1990, 1992, 1994, 1996, 1998]
+    );
+    assert_eq!(keys(index.range(..4)), vec![0, 2]);
+    assert_eq!(
+        keys(index.range((Bound::Excluded(100), Bound::Excluded(106)))),
+        vec![102, 104]
+    );
+    assert_eq!(keys(index.range(2000..)), Vec::<u32>::new());
+    assert_eq!(keys(index.range(51..52)), Vec::<u32>::new());
+    assert_eq!(
+        keys(Box::new(index.range(500..520).rev())),
+        vec![518, 516, 514, 512, 510, 508, 506, 504, 502, 500]
+    );
+    assert_eq!(
+        index.range(300..=400).map(|(_, value)| value).sum::<u32>(),
+        (150..=200).sum::<u32>()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn node_remove_rebalance() -> Result<(), Box<dyn std::error::Error>> {
+    let mut index = crate::RootNode::<u32, u32>::with_fanout(crate::Fanout::new(4, 5));
+
+    for i in 0..2000 {
+        let key = i * 7919 % 2000;
+        index.insert(&key, key)?;
+    }
+
+    for i in 0..2000 {
+        let key = i * 4999 % 2000;
+        if key % 10 != 0 {
+            index.remove(&key)?;
+        }
+    }
+    assert_eq!(
+        index.range(..).map(|(key, _)| *key).collect::<Vec<_>>(),
+        (0..200).map(|i| i * 10).collect::<Vec<_>>()
+    );
+    assert!(index.check().is_ok());
+    for i in 0..200 {
+        assert_eq!(crate::Node::find(&index, &(i * 10)), Some(&(i * 10)));
+    }
+
+    for i in (0..200).rev() {
+        index.remove(&(i * 10))?;
+    }
+    assert!(crate::Node::collect(&index).is_empty());
+    assert!(index.check().is_ok());
+
+    index.insert(&1, 1)?;
+    assert_eq!(crate::Node::collect(&index), vec![(1, 1)]);
+
+    Ok(())
+}
+
+#[test]
+fn node_from_sorted_iter() -> Result<(), Box<dyn std::error::Error>> {
+    let mut index = crate::RootNode::<u32, u32>::from_sorted_iter(
+        crate::Fanout::new(4, 5),
+        (0..1000).map(|i| (i, i)),
+    )?;
+    assert!(index.check().is_ok());
+    assert_eq!(
+        crate::Node::collect(&index),
+        (0..1000).map(|i| (i, i)).collect::<Vec<_>>()
+    );
+
+    index.insert(&1000, 1000)?;
+    for i in 0..500 {
+        index.remove(&(i * 2))?;
+    }
+    assert_eq!(
+        index.range(..).map(|(key, _)| *key).collect::<Vec<_>>(),
+        (0..500)
+            .map(|i| i * 2 + 1)
+            .chain(Some(1000))
+            .collect::<Vec<_>>()
+    );
+
+    let index =
+        crate::RootNode::<u32, u32>::from_sorted_iter(crate::Fanout::new(4, 5), Vec::new())?;
+    assert!(crate::Node::collect(&index).is_empty());
+
+    assert!(crate::RootNode::<u32, u32>::from_sorted_iter(
+        crate::Fanout::new(4, 5),
+        vec![(1, 1), (1, 1)]
+    )
+    .is_err());
+    assert!(crate::RootNode::<u32, u32>::from_sorted_iter(
+        crate::Fanout::new(4, 5),
+        vec![(2, 2), (1, 1)]
+    )
+    .is_err());
+    assert!(
+        crate::RootNode::<u32, u32>::from_sorted_iter(crate::Fanout::new(1, 2), vec![(1, 1)])
+            .is_err()
+    );
+
+    let mut index = crate::RootNode::<u32, u32>::from_sorted_iter(
+        crate::Fanout::new(8, 3),
+        (0..1000).map(|i| (i, i)),
+    )?;
+    for i in 1000..1100 {
+        index.insert(&i, i)?;
+    }
+    assert!(index.check().is_ok(), "{}", index.check());
+    assert_eq!(index.stats().nodes_per_level[1], 3);
+
+    Ok(())
+}
+
+#[test]
+fn node_entry() -> Result<(), Box<dyn std::error::Error>> {
+    let mut index = crate::RootNode::<u32, u32>::with_fanout(crate::Fanout::new(4, 5));
+
+    for i in 0..100 {
+        *index.entry(i % 10).or_insert(0) += 1;
+    }
+    for i in 0..10 {
+        assert_eq!(crate::Node::find(&index, &i), Some(&10));
+    }
+
+    index.entry(3).and_modify(|value| *value = 30).or_default();
+    index
+        .entry(10)
+        .and_modify(|value| *value = 100)
+        .or_default();
+    assert_eq!(crate::Node::find(&index, &3), Some(&30));
+    assert_eq!(crate::Node::find(&index, &10), Some(&0));
+
+    if let Some(value) = crate::Node::get_mut(&mut index, &5) {
+        *value *= 5;
+    }
+    assert_eq!(crate::Node::find(&index, &5), Some(&50));
+    assert_eq!(crate::Node::get_mut(&mut index, &11), None);
+
+    match index.entry(7) {
+        crate::Entry::Occupied(mut entry) => assert_eq!(entry.insert(70), 10),
+        crate::Entry::Vacant(_) => panic!("key 7 must be occupied"),
+    }
+    assert_eq!(crate::Node::find(&index, &7), Some(&70));
+
+    // Vacant entries that split nodes on the way up still hand out the value
+    // of the key they were made for.
+    let mut index = crate::RootNode::<u32, u32>::with_fanout(crate::Fanout::new(2, 3));
+    let scattered = (0..200).map(|i| (i * 37) % 200);
+    for i in scattered.chain((200..300).rev()).chain(300..400) {
+        *index.entry(i).or_default() += i;
+        *index.entry(i).or_default() += 1;
+    }
+    assert!(index.check().is_ok(), "{}", index.check());
+    for i in 0..400 {
+        assert_eq!(crate::Node::find(&index, &i), Some(&(i + 1)));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn node_order_statistics() -> Result<(), Box<dyn std::error::Error>> {
+    use std::ops::Bound;
+    let mut index = crate::RootNode::<u32, u32>::with_fanout(crate::Fanout::new(4, 5));
+    let mut model = std::collections::BTreeSet::new();
+
+    for i in 0..3000 {
+        let key = i * 7919 % 1000;
+        if i % 3 == 2 {
+            if model.remove(&key) {
+                index.remove(&key)?;
+            }
+        } else if model.insert(key) {
+            index.insert(&key, key)?;
+        }
+        assert_eq!(index.len(), model.len());
+    }
+    assert!(index.check().is_ok());
+
+    let keys = model.iter().copied().collect::<Vec<_>>();
+    for (idx, key) in keys.iter().enumerate() {
+        assert_eq!(index.nth(idx), Some((key, key)));
+        assert_eq!(index.rank(key), idx);
+    }
+    assert_eq!(index.nth(keys.len()), None);
+    assert_eq!(index.rank(&1000), keys.len());
+
+    for (start, end) in [(0, 1000), (100, 200), (250, 251), (400, 400), (999, 1000)] {
+        assert_eq!(
+            index.count_range(start..end),
+            model.range(start..end).count()
+        );
+        assert_eq!(
+            index.count_range(start..=end),
+            model.range(start..=end).count()
+        );
+    }
+    assert_eq!(
+        index.count_range((Bound::Included(500), Bound::Excluded(400))),
+        0
+    );
+    assert_eq!(index.count_range(..), model.len());
+    assert_eq!(index.count_range(..500), model.range(..500).count());
+
+    Ok(())
+}
+
+#[test]
+fn node_floor_ceiling() -> Result<(), Box<dyn std::error::Error>> {
+    let mut index = crate::RootNode::<u32, u32>::with_fanout(crate::Fanout::new(4, 5));
+    assert_eq!(crate::Node::first(&index), None);
+    assert_eq!(index.pop_last(), None);
+
+    for i in 1..=100 {
+        index.insert(&(i * 10), i)?;
+    }
+
+    assert_eq!(crate::Node::first(&index), Some((&10, &1)));
+    assert_eq!(crate::Node::last(&index), Some((&1000, &100)));
+    assert_eq!(crate::Node::floor(&index, &255), Some((&250, &25)));
+    assert_eq!(crate::Node::floor(&index, &250), Some((&250, &25)));
+    assert_eq!(crate::Node::floor(&index, &5), None);
+    assert_eq!(crate::Node::ceiling(&index, &255), Some((&260, &26)));
+    assert_eq!(crate::Node::ceiling(&index, &1001), None);
+
+    for i in 1..=50 {
+        assert_eq!(index.pop_first(), Some((i * 10, i)));
+        assert_eq!(index.pop_last(), Some((1010 - i * 10, 101 - i)));
+    }
+    assert_eq!(index.pop_first(), None);
+    assert!(index.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn node_check() -> Result<(), Box<dyn std::error::Error>> {
+    let mut index = crate::RootNode::<u32, u32>::with_fanout(crate::Fanout::new(3, 4));
+    let report = index.check();
+    assert!(report.is_ok());
+    assert_eq!((report.entries, report.height), (0, 0));
+
+    for i in 0..100 {
+        index.insert(&(i * 37 % 100), i)?;
+        assert!(index.check().is_ok(), "{}", index.check());
+    }
+    let report = index.check();
+    assert_eq!(report.entries, 100);
+    assert!(report.height >= 4);
+
+    for i in 0..100 {
+        index.remove(&(i * 53 % 100))?;
+        assert!(index.check().is_ok(), "{}", index.check());
+    }
+    assert_eq!(index.check().height, 0);
+
+    Ok(())
+}
+
+#[test]
+fn node_debug_and_stats() -> Result<(), Box<dyn std::error::Error>> {
+    let mut index = crate::RootNode::<u32, u32>::with_fanout(crate::Fanout::new(2, 3));
+    assert_eq!(index.stats().height, 0);
+    assert_eq!(format!("{:?}", index), "RootNode<2, 3> (0)\n");
+
+    for i in 0..6 {
+        index.insert(&i, i * 10)?;
+    }
+    assert_eq!(
+        format!("{:.1?}", index),
+        [
+            "RootNode<2, 3> (6)",
+            "  <= 1 (2)",
+            "    leaf (2) { 0: 0, … 1 more }",
+            "  <= 3 (2)",
+            "    leaf (2) { 2: 20, … 1 more }",
+            "  <= 5 (2)",
+            "    leaf (2) { 4: 40, … 1 more }",
+            "",
+        ]
+        .join("\n")
+    );
+
+    for i in 6..10 {
+        index.insert(&i, i * 10)?;
+    }
+    let stats = index.stats();
+    assert_eq!(stats.entries, 10);
+    assert_eq!(stats.height, 2);
+    assert_eq!(stats.nodes_per_level, vec![1, 2, 5]);
+    assert_eq!(stats.leaf_fill_factor, 1.0);
+
+    Ok(())
+}
+
+#[test]
+fn node_snapshot() -> Result<(), Box<dyn std::error::Error>> {
+    let mut index = crate::RootNode::<u32, u32>::with_fanout(crate::Fanout::new(4, 5));
+    for i in 0..1000 {
+        index.insert(&i, i)?;
+    }
+
+    let snapshot = index.snapshot();
+    let reader = std::thread::spawn(move || {
+        (0..1000).all(|i| crate::Node::find(&*snapshot, &i) == Some(&i))
+            && snapshot.len() == 1000
+            && snapshot.check().is_ok()
+    });
+
+    let snapshot = index.snapshot();
+    for i in 0..500 {
+        index.remove(&(i * 2))?;
+        index.update(&(i * 2 + 1), 0)?;
+    }
+    index.insert(&1000, 1000)?;
+
+    assert!(reader.join().unwrap());
+    assert_eq!(
+        snapshot
+            .range(..)
+            .map(|(key, value)| (*key, *value))
+            .collect::<Vec<_>>(),
+        (0..1000).map(|i| (i, i)).collect::<Vec<_>>()
+    );
+    assert_eq!(index.len(), 501);
+    assert_eq!(crate::Node::find(&index, &1), Some(&0));
+    assert!(index.check().is_ok());
+    assert!(snapshot.check().is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn node_multimap() -> Result<(), Box<dyn std::error::Error>> {
+    let mut index = crate::MultiMap::<u32, u32>::with_fanout(crate::Fanout::new(3, 4));
+    for i in 0..300 {
+        index.insert(i % 10, 300 - i)?;
+    }
+    assert!(index.insert(0, 300).is_err());
+    assert_eq!(index.len(), 300);
+    assert_eq!(index.count(&3), 30);
+    assert_eq!(index.count(&10), 0);
+
+    let values = index.find_all(&3).copied().collect::<Vec<_>>();
+    assert_eq!(
+        values,
+        (0..30).rev().map(|i| 297 - i * 10).collect::<Vec<_>>()
+    );
+    assert_eq!(index.find_all(&3).next_back(), Some(&297));
+
+    index.remove_one(&3, &297)?;
+    assert!(index.remove_one(&3, &297).is_err());
+    assert!(!index.contains(&3, &297));
+    assert!(index.contains(&3, &287));
+    assert_eq!(index.count(&3), 29);
+
+    let keys = index.range(2..4).map(|(key, _)| *key).collect::<Vec<_>>();
+    assert_eq!(keys, [vec![2; 30], vec![3; 29]].concat());
+    assert_eq!(index.range(..).count(), 299);
+    assert_eq!(index.range(9..).next(), Some((&9, &1)));
+
+    Ok(())
+}
+
+#[test]
+fn node_remove_range_split_append() -> Result<(), Box<dyn std::error::Error>> {
+    let fanout = crate::Fanout::new(3, 4);
+    let mut index =
+        crate::RootNode::<u32, u32>::from_sorted_iter(fanout, (0..1000).map(|i| (i, i)))?;
+    let snapshot = index.snapshot();
+
+    assert_eq!(index.remove_range(100..900), 800);
+    assert_eq!(index.remove_range(100..900), 0);
+    assert!(index.check().is_ok(), "{}", index.check());
+    assert_eq!(index.len(), 200);
+    assert_eq!(index.nth(100), Some((&900, &900)));
+    assert_eq!(snapshot.len(), 1000);
+
+    let mut upper = index.split_off(&950);
+    assert!(index.check().is_ok(), "{}", index.check());
+    assert!(upper.check().is_ok(), "{}", upper.check());
+    assert_eq!(crate::Node::last(&index), Some((&949, &949)));
+    assert_eq!(crate::Node::first(&upper), Some((&950, &950)));
+    assert_eq!((index.len(), upper.len()), (150, 50));
+
+    let mut lower = index.split_off(&0);
+    std::mem::swap(&mut index, &mut lower);
+    assert!(lower.is_empty());
+    upper.append(&mut index);
+    assert!(index.is_empty());
+    assert!(upper.check().is_ok(), "{}", upper.check());
+    assert_eq!(
+        crate::Node::collect(&upper),
+        (0..100)
+            .chain(900..1000)
+            .map(|i| (i, i))
+            .collect::<Vec<_>>()
+    );
+
+    let mut overlapping = crate::RootNode::with_fanout(fanout);
+    overlapping.insert(&50, 0)?;
+    overlapping.insert(&500, 0)?;
+    upper.append(&mut overlapping);
+    assert!(upper.check().is_ok(), "{}", upper.check());
+    assert_eq!(upper.len(), 201);
+    assert_eq!(crate::Node::find(&upper, &50), Some(&0));
+
+    Ok(())
+}
+
+#[test]
+fn node_iterators() -> Result<(), Box<dyn std::error::Error>> {
+    let index: crate::RootNode<u32, u32> = (0..100).rev().map(|i| (i % 50, i)).collect();
+    assert!(index.check().is_ok(), "{}", index.check());
+    assert_eq!(index.len(), 50);
+    assert_eq!(crate::Node::find(&index, &7), Some(&7));
+    assert!(index.keys().copied().eq(0..50));
+    assert_eq!(index.values().next_back(), Some(&49));
+
+    let mut extended = crate::RootNode::with_fanout(crate::Fanout::new(3, 4));
+    extended.extend((0..50).map(|i| (i, i + 50)));
+    extended.extend((0..50).map(|i| (i, i)));
+    assert_eq!(extended, index);
+    assert_ne!(extended, crate::RootNode::new());
+
+    let snapshot = index.snapshot();
+    let mut into_iter = index.into_iter();
+    assert_eq!(into_iter.len(), 50);
+    assert_eq!(into_iter.next(), Some((0, 0)));
+    assert_eq!(into_iter.next_back(), Some((49, 49)));
+    assert!(into_iter.eq((1..49).map(|i| (i, i))));
+
+    let mut borrowed = 0;
+    for (key, value) in &*snapshot {
+        assert_eq!(key, value);
+        borrowed += 1;
+    }
+    assert_eq!(borrowed, 50);
+
+    Ok(())
+}
+
+#[test]
+fn node_prefix_tree() -> Result<(), Box<dyn std::error::Error>> {
+    let mut index = crate::PrefixTree::<String, u32>::with_fanout(crate::Fanout::new(3, 4));
+    for i in 0..500 {
+        index.insert(&format!("/var/lib/database/table{}/row{:04}", i % 5, i), i)?;
+    }
+    assert!(index
+        .insert(&"/var/lib/database/table0/row0000".to_string(), 0)
+        .is_err());
+    index.insert(&"/etc/hosts".to_string(), 500)?;
+    assert_eq!(index.len(), 501);
+    assert_eq!(
+        index.find(&"/var/lib/database/table3/row0123".to_string()),
+        Some(&123)
+    );
+    assert_eq!(index.find(&"/var/lib/database/table3".to_string()), None);
+
+    let table3 = "/var/lib/database/table3/".to_string();
+    let table4 = "/var/lib/database/table4/".to_string();
+    let keys = index
+        .range(table3..table4)
+        .map(|(key, _)| key)
+        .collect::<Vec<_>>();
+    assert_eq!(keys.len(), 100);
+    assert_eq!(keys[0], "/var/lib/database/table3/row0003");
+    assert_eq!(index.iter().next(), Some(("/etc/hosts".to_string(), &500)));
+
+    for i in (0..500).filter(|i| i % 5 != 3) {
+        assert_eq!(
+            index.remove(&format!("/var/lib/database/table{}/row{:04}", i % 5, i))?,
+            i
+        );
+    }
+    index.update(&"/etc/hosts".to_string(), 0)?;
+    assert_eq!(index.len(), 101);
+    assert_eq!(
+        index.iter().rev().nth(100),
+        Some(("/etc/hosts".to_string(), &0))
+    );
+
+    // Keys landing before and after every stored one shorten the prefixes
+    // of the nodes they end up in, and removals merge nodes again.
+    let mut index = crate::PrefixTree::<String, u32>::with_fanout(crate::Fanout::new(3, 4));
+    let mut expected = std::collections::BTreeMap::new();
+    for i in 0..2000 {
+        let i = i * 7919 % 2000;
+        let key = format!("/{}/{}/{:04}", "ab".repeat(i as usize % 7), i % 3, i);
+        index.insert(&key, i)?;
+        expected.insert(key, i);
+    }
+    for i in (0..2000).filter(|i| i % 3 != 0) {
+        let key = format!("/{}/{}/{:04}", "ab".repeat(i as usize % 7), i % 3, i);
+        assert_eq!(index.remove(&key)?, expected.remove(&key).unwrap());
+    }
+    assert!(index
+        .iter()
+        .map(|(key, value)| (key, *value))
+        .eq(expected.into_iter()));
+
+    Ok(())
+}
+
+#[test]
+fn node_collation() -> Result<(), Box<dyn std::error::Error>> {
+    let folder_path = super::temp_dir("node_collation");
+    let mut wal = crate::Wal::open(&folder_path)?;
+    let mut index = crate::RootNode::<String, usize, _>::with_collation(
+        crate::Fanout::new(4, 5),
+        crate::CaseInsensitive,
+    );
+
+    for i in 0..100 {
+        index.insert(&format!("Key{:03}", i), i)?;
+    }
+    assert!(index.insert(&"KEY007".to_string(), 0).is_err());
+    assert_eq!(crate::Node::find(&index, &"key042".to_string()), Some(&42));
+    assert!(index.check().is_ok());
+
+    crate::dump(&index, &mut wal)?;
+    assert!(crate::load::<String, usize>(&folder_path).is_err());
+    // A comparator chosen at run time reads the dump as well.
+    let collation = crate::Collation::<String>::new(crate::CaseInsensitive);
+    let index = crate::load_collated::<String, usize, _>(&folder_path, collation)?;
+    assert_eq!(
+        crate::Comparator::name(index.collation()),
+        "case-insensitive"
+    );
+    assert_eq!(crate::Node::find(&index, &"KEY099".to_string()), Some(&99));
+    let shouted: crate::RootNode<String, usize, _> = crate::RootNode::from_sorted_iter_with(
+        crate::Fanout::default(),
+        crate::Collation::new(crate::CaseInsensitive),
+        (0..100).map(|i| (format!("KEY{:03}", i), i)),
+    )?;
+    assert!(index == shouted);
+
+    let mut multimap = crate::MultiMap::<String, usize, _>::with_collation(
+        crate::Fanout::new(4, 5),
+        crate::CaseInsensitive,
+    );
+    multimap.insert("a".to_string(), 1)?;
+    multimap.insert("A".to_string(), 2)?;
+    assert_eq!(multimap.count(&"a".to_string()), 2);
+    std::fs::remove_dir_all(&folder_path)?;
+    Ok(())
+}
+
+#[test]
+fn node_errors() -> Result<(), Box<dyn std::error::Error>> {
+    let mut index = crate::RootNode::<u32, u32>::with_fanout(crate::Fanout::new(2, 3));
+    for i in 0..100 {
+        index.insert(&i, i)?;
+        assert_eq!(index.insert(&i, 0), Err(crate::NodeError::Duplicated));
+    }
+    assert_eq!(index.update(&100, 0), Err(crate::NodeError::NotFound));
+    assert_eq!(index.remove(&100), Err(crate::NodeError::NotFound));
+    assert_eq!(index.len(), 100);
+    assert!(index.check().is_ok());
+    Ok(())
+}
+
+#[test]
+fn node_dump_formats() -> Result<(), Box<dyn std::error::Error>> {
+    use sha2::Digest;
+
+    let folder_path = super::temp_dir("node_dump_formats");
+    std::fs::create_dir_all(folder_path.join("commit"))?;
+
+    // Files as written before the versioned format.
+    let write_legacy = |path: std::path::PathBuf, json: &str| {
+        let mut bytes = (json.len() as u64).to_le_bytes().to_vec();
+        bytes.extend_from_slice(sha2::Sha512::digest(json.as_bytes()).as_slice());
+        bytes.extend_from_slice(json.as_bytes());
+        std::fs::write(path, bytes)
+    };
+    write_legacy(
+        folder_path.join("full_dump.json"),
+        r#"[["a","1"],["b","2"]]"#,
+    )?;
+    write_legacy(
+        folder_path.join("commit").join("1.json"),
+        r#"{"c":{"Insert":"3"}}"#,
+    )?;
+    // The last commit file was cut short by a crash.
+    write_legacy(
+        folder_path.join("commit").join("2.json"),
+        r#"{"d":{"Insert":"4"}}"#,
+    )?;
+    let torn_path = folder_path.join("commit").join("2.json");
+    let len = std::fs::metadata(&torn_path)?.len();
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(&torn_path)?
+        .set_len(len - 1)?;
+    let recovery = crate::recover::<String, String, _>(&folder_path, crate::Natural)?;
+    let torn_tail = recovery.torn_tail.ok_or("no torn tail")?;
+    assert_eq!(torn_tail.path, torn_path);
+    assert_eq!(torn_tail.len, len - 1);
+    let index = recovery.root_node;
+    assert_eq!(index.len(), 3);
+    assert_eq!(
+        crate::Node::find(&index, &"c".to_string()),
+        Some(&"3".to_string())
+    );
+
+    let mut wal = crate::Wal::open(&folder_path)?;
+    assert!(wal.torn_tail().is_some());
+    assert!(!torn_path.exists());
+    for format in [crate::Format::Json, crate::Format::Binary] {
+        crate::dump_as(&index, &mut wal, format)?;
+        assert!(!folder_path.join("full_dump.json").exists());
+        assert_eq!(crate::load::<String, String>(&folder_path)?, index);
+    }
+    std::fs::remove_dir_all(&folder_path)?;
+    Ok(())
+}
@@ -0,0 +1,127 @@
+#[test]
+fn paged_insert_reopen() -> Result<(), Box<dyn std::error::Error>> {
+    let folder_path = super::temp_dir("paged_insert_reopen");
+    std::fs::create_dir_all(&folder_path)?;
+    let path = folder_path.join("tree.db");
+    {
+        let mut tree = crate::PagedTree::<String, String>::open(&path, 8)?;
+        for i in 0..5000 {
+            tree.insert(&format!("key{:05}", i), format!("value{}", i))?;
+        }
+        assert!(matches!(
+            tree.insert(&"key00042".to_string(), String::new()),
+            Err(crate::PagedError::Duplicated)
+        ));
+        tree.update(&"key00042".to_string(), "updated".to_string())?;
+        assert_eq!(
+            tree.find(&"key00042".to_string())?,
+            Some("updated".to_string())
+        );
+    }
+
+    let tree = crate::PagedTree::<String, String>::open(&path, 8)?;
+    assert_eq!(tree.len(), 5000);
+    for i in (0..5000).step_by(7) {
+        let expected = if i == 42 {
+            "updated".to_string()
+        } else {
+            format!("value{}", i)
+        };
+        assert_eq!(tree.find(&format!("key{:05}", i))?, Some(expected));
+    }
+    assert_eq!(tree.find(&"missing".to_string())?, None);
+    Ok(())
+}
+
+#[test]
+fn paged_remove_then_range() -> Result<(), Box<dyn std::error::Error>> {
+    let folder_path = super::temp_dir("paged_remove_then_range");
+    std::fs::create_dir_all(&folder_path)?;
+    let path = folder_path.join("tree.db");
+    let mut tree = crate::PagedTree::<u64, u64>::open(&path, 4)?;
+    for i in 0..3000 {
+        tree.insert(&(i * 2), i)?;
+    }
+    for i in 0..2500 {
+        assert_eq!(tree.remove(&(i * 2))?, i);
+    }
+    assert!(matches!(tree.remove(&1), Err(crate::PagedError::NotFound)));
+    assert_eq!(tree.len(), 500);
+
+    let keys = tree
+        .range(5000..=5010)?
+        .map(|pair| pair.map(|(key, _)| key))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(keys, vec![5000, 5002, 5004, 5006, 5008, 5010]);
+
+    let keys = tree
+        .range(..)?
+        .rev()
+        .map(|pair| pair.map(|(key, _)| key))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(
+        keys,
+        (2500..3000).rev().map(|i| i * 2).collect::<Vec<u64>>()
+    );
+
+    let mut range = tree.range(5001..5995)?;
+    assert_eq!(range.next().transpose()?, Some((5002, 2501)));
+    assert_eq!(range.next_back().transpose()?, Some((5994, 2997)));
+    assert_eq!(range.count(), 495);
+
+    for i in 2500..3000 {
+        tree.remove(&(i * 2))?;
+    }
+    assert!(tree.is_empty());
+    assert_eq!(tree.first()?, None);
+    Ok(())
+}
+
+#[test]
+fn paged_entry_too_large() -> Result<(), Box<dyn std::error::Error>> {
+    let folder_path = super::temp_dir("paged_entry_too_large");
+    std::fs::create_dir_all(&folder_path)?;
+    let path = folder_path.join("tree.db");
+    let mut tree = crate::PagedTree::<u64, String>::open(&path, 4)?;
+    let value = "x".repeat(crate::paged::MAX_ENTRY_SIZE);
+    assert!(matches!(
+        tree.insert(&0, value),
+        Err(crate::PagedError::EntryTooLarge(_))
+    ));
+    assert!(tree.is_empty());
+    Ok(())
+}
+
+#[test]
+fn paged_large_keys() -> Result<(), Box<dyn std::error::Error>> {
+    let folder_path = super::temp_dir("paged_large_keys");
+    std::fs::create_dir_all(&folder_path)?;
+    let path = folder_path.join("tree.db");
+    let key = |i: usize| format!("{:04}{}", i, "k".repeat(150));
+    {
+        let mut tree = crate::PagedTree::<String, u8>::open(&path, 8)?;
+        for i in 0..400 {
+            tree.insert(&key(i), 0)?;
+        }
+        // Rejecting a key past every bound leaves the bounds as they were.
+        assert!(matches!(
+            tree.insert(&"9".repeat(1500), 0),
+            Err(crate::PagedError::EntryTooLarge(_))
+        ));
+        tree.flush()?;
+
+        // Raising the last bounds to longer keys splits the pages holding
+        // them once they no longer fit.
+        for i in 400..440 {
+            tree.insert(&format!("{:04}{}", i, "k".repeat(990)), 1)?;
+        }
+        tree.flush()?;
+    }
+
+    let tree = crate::PagedTree::<String, u8>::open(&path, 8)?;
+    assert_eq!(tree.len(), 440);
+    for i in 0..400 {
+        assert_eq!(tree.find(&key(i))?, Some(0));
+    }
+    Ok(())
+}
@@ -1,14 +1,14 @@
 #[test]
 fn simple_secondary_index() -> Result<(), Box<dyn std::error::Error>> {
-    let root_node = crate::RootNode::<String, String, 10>::new();
+    let root_node = crate::RootNode::<String, String>::with_fanout(crate::Fanout::new(10, 11));
     let mut secondaries: std::collections::HashMap<
         String,
-        Box<dyn crate::table::SecondaryIndex<String, String, 10>>,
+        Box<dyn crate::table::SecondaryIndex<String, String>>,
     > = std::collections::HashMap::new();
     secondaries.insert(
         "value".to_string(),
         Box::new(crate::DefaultSecondaryIndex::new(
-            |x| crate::Primitive::String(x),
+            crate::Primitive::String,
             |x| {
                 if let crate::Primitive::String(x) = x {
                     Some(x)
@@ -47,15 +47,17 @@ fn simple_secondary_index() -> Result<(), Box<dyn std::error::Error>> {
 
 #[test]
 fn secondary_index_with_commit() -> Result<(), Box<dyn std::error::Error>> {
-    let root_node = crate::RootNode::<String, String, 10>::new();
+    let folder_path = super::temp_dir("secondary_index_with_commit");
+    let mut wal = crate::Wal::open(&folder_path)?;
+    let root_node = crate::RootNode::<String, String>::with_fanout(crate::Fanout::new(10, 11));
     let mut secondaries: std::collections::HashMap<
         String,
-        Box<dyn crate::table::SecondaryIndex<String, String, 10>>,
+        Box<dyn crate::table::SecondaryIndex<String, String>>,
     > = std::collections::HashMap::new();
     secondaries.insert(
         "value".to_string(),
         Box::new(crate::DefaultSecondaryIndex::new(
-            |x| crate::Primitive::String(x),
+            crate::Primitive::String,
             |x| {
                 if let crate::Primitive::String(x) = x {
                     Some(x)
@@ -76,7 +78,7 @@ fn secondary_index_with_commit() -> Result<(), Box<dyn std::error::Error>> {
         "key".to_string(),
         "value".to_string(),
     )))?;
-    transaction.commit(std::path::Path::new("./data"))?;
+    transaction.commit(&mut wal)?;
 
     let transaction = crate::Transaction::new(&mut table);
     assert_eq!(
@@ -96,15 +98,17 @@ fn secondary_index_with_commit() -> Result<(), Box<dyn std::error::Error>> {
 
 #[test]
 fn secondary_index_update() -> Result<(), Box<dyn std::error::Error>> {
-    let root_node = crate::RootNode::<String, String, 10>::new();
+    let folder_path = super::temp_dir("secondary_index_update");
+    let mut wal = crate::Wal::open(&folder_path)?;
+    let root_node = crate::RootNode::<String, String>::with_fanout(crate::Fanout::new(10, 11));
     let mut secondaries: std::collections::HashMap<
         String,
-        Box<dyn crate::table::SecondaryIndex<String, String, 10>>,
+        Box<dyn crate::table::SecondaryIndex<String, String>>,
     > = std::collections::HashMap::new();
     secondaries.insert(
         "value".to_string(),
         Box::new(crate::DefaultSecondaryIndex::new(
-            |x| crate::Primitive::String(x),
+            crate::Primitive::String,
             |x| {
                 if let crate::Primitive::String(x) = x {
                     Some(x)
@@ -125,7 +129,7 @@ fn secondary_index_update() -> Result<(), Box<dyn std::error::Error>> {
         "key".to_string(),
         "value".to_string(),
     )))?;
-    transaction.commit(std::path::Path::new("./data"))?;
+    transaction.commit(&mut wal)?;
 
     let mut transaction = crate::Transaction::new(&mut table);
     transaction.exec(crate::Request::Update((
@@ -150,15 +154,17 @@ fn secondary_index_update() -> Result<(), Box<dyn std::error::Error>> {
 
 #[test]
 fn secondary_index_remove() -> Result<(), Box<dyn std::error::Error>> {
-    let root_node = crate::RootNode::<String, String, 10>::new();
+    let folder_path = super::temp_dir("secondary_index_remove");
+    let mut wal = crate::Wal::open(&folder_path)?;
+    let root_node = crate::RootNode::<String, String>::with_fanout(crate::Fanout::new(10, 11));
     let mut secondaries: std::collections::HashMap<
         String,
-        Box<dyn crate::table::SecondaryIndex<String, String, 10>>,
+        Box<dyn crate::table::SecondaryIndex<String, String>>,
     > = std::collections::HashMap::new();
     secondaries.insert(
         "value".to_string(),
         Box::new(crate::DefaultSecondaryIndex::new(
-            |x| crate::Primitive::String(x),
+            crate::Primitive::String,
             |x| {
                 if let crate::Primitive::String(x) = x {
                     Some(x)
@@ -179,7 +185,7 @@ fn secondary_index_remove() -> Result<(), Box<dyn std::error::Error>> {
         "key".to_string(),
         "value".to_string(),
     )))?;
-    transaction.commit(std::path::Path::new("./data"))?;
+    transaction.commit(&mut wal)?;
 
     let mut transaction = crate::Transaction::new(&mut table);
     transaction.exec(crate::Request::Remove("key".to_string()))?;
@@ -194,3 +200,160 @@ fn secondary_index_remove() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn secondary_index_snapshot() -> Result<(), Box<dyn std::error::Error>> {
+    let folder_path = super::temp_dir("secondary_index_snapshot");
+    let mut wal = crate::Wal::open(&folder_path)?;
+    let root_node = crate::RootNode::<String, String>::with_fanout(crate::Fanout::new(2, 3));
+    let mut secondaries: std::collections::HashMap<
+        String,
+        Box<dyn crate::table::SecondaryIndex<String, String>>,
+    > = std::collections::HashMap::new();
+    secondaries.insert(
+        "value".to_string(),
+        Box::new(crate::DefaultSecondaryIndex::with_fanout(
+            crate::Primitive::String,
+            |x| {
+                if let crate::Primitive::String(x) = x {
+                    Some(x)
+                } else {
+                    None
+                }
+            },
+            crate::Fanout::new(2, 3),
+        )),
+    );
+
+    let mut table = crate::Table {
+        primary: root_node,
+        secondaries,
+    };
+
+    let mut transaction = crate::Transaction::new(&mut table);
+    for i in 0..10 {
+        transaction.exec(crate::Request::Insert((
+            format!("key{}", i),
+            format!("value{}", i % 2),
+        )))?;
+    }
+    transaction.commit(&mut wal)?;
+
+    let snapshot = table.snapshot();
+    let mut transaction = crate::Transaction::new(&mut table);
+    transaction.exec(crate::Request::Update((
+        "key0".to_string(),
+        "value1".to_string(),
+    )))?;
+    transaction.exec(crate::Request::Remove("key2".to_string()))?;
+    transaction.commit(&mut wal)?;
+
+    let select = |keys: std::collections::HashSet<String>| {
+        let mut keys: Vec<String> = keys.into_iter().collect();
+        keys.sort();
+        keys
+    };
+    let even = crate::Primitive::String("value0".to_string());
+    assert_eq!(
+        select(snapshot.select(&"value".to_string(), &even)?),
+        vec!["key0", "key2", "key4", "key6", "key8"]
+    );
+    assert_eq!(
+        snapshot.find(&"key0".to_string()),
+        Some(&"value0".to_string())
+    );
+    let page = crate::Cursor::start().next_page(&snapshot, 3);
+    assert_eq!(
+        page.entries,
+        vec![
+            ("key0".to_string(), "value0".to_string()),
+            ("key1".to_string(), "value1".to_string()),
+            ("key2".to_string(), "value0".to_string()),
+        ]
+    );
+
+    let transaction = crate::Transaction::new(&mut table);
+    assert_eq!(
+        select(transaction.select(&"value".to_string(), &even)?),
+        vec!["key4", "key6", "key8"]
+    );
+    assert_eq!(snapshot.len(), 10);
+    assert_eq!(table.primary.len(), 9);
+
+    std::fs::remove_dir_all(&folder_path)?;
+    Ok(())
+}
+
+#[test]
+fn secondary_index_snapshot_threads() -> Result<(), Box<dyn std::error::Error>> {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<crate::TableSnapshot<String, String>>();
+
+    let folder_path = super::temp_dir("secondary_index_snapshot_threads");
+    let mut wal = crate::Wal::open(&folder_path)?;
+    let root_node = crate::RootNode::<String, String>::with_fanout(crate::Fanout::new(2, 3));
+    let mut secondaries: std::collections::HashMap<
+        String,
+        Box<dyn crate::table::SecondaryIndex<String, String>>,
+    > = std::collections::HashMap::new();
+    secondaries.insert(
+        "value".to_string(),
+        Box::new(crate::DefaultSecondaryIndex::new(
+            crate::Primitive::String,
+            |x| {
+                if let crate::Primitive::String(x) = x {
+                    Some(x)
+                } else {
+                    None
+                }
+            },
+        )),
+    );
+
+    let mut table = crate::Table {
+        primary: root_node,
+        secondaries,
+    };
+
+    let mut transaction = crate::Transaction::new(&mut table);
+    for i in 0..100 {
+        transaction.exec(crate::Request::Insert((
+            format!("key{:03}", i),
+            "old".to_string(),
+        )))?;
+    }
+    transaction.commit(&mut wal)?;
+
+    // The reader keeps seeing the table as it was while the writer rewrites
+    // every row under it.
+    let snapshot = table.snapshot();
+    let reader = std::thread::spawn(move || {
+        (0..100).all(|_| {
+            let old = crate::Primitive::String("old".to_string());
+            snapshot.len() == 100
+                && snapshot.range(..).all(|(_, value)| value == "old")
+                && snapshot
+                    .select(&"value".to_string(), &old)
+                    .is_ok_and(|keys| keys.len() == 100)
+        })
+    });
+    for i in 0..100 {
+        let mut transaction = crate::Transaction::new(&mut table);
+        let key = format!("key{:03}", i);
+        if i % 2 == 0 {
+            transaction.exec(crate::Request::Remove(key))?;
+        } else {
+            transaction.exec(crate::Request::Update((key, "new".to_string())))?;
+        }
+        transaction.commit(&mut wal)?;
+    }
+    assert!(reader.join().map_err(|_| "reader panicked")?);
+
+    let snapshot = table.snapshot();
+    assert_eq!(snapshot.len(), 50);
+    let new = crate::Primitive::String("new".to_string());
+    assert_eq!(snapshot.select(&"value".to_string(), &new)?.len(), 50);
+
+    std::fs::remove_dir_all(&folder_path)?;
+    Ok(())
+}
@@ -1,10 +1,7 @@
 #[test]
 fn transaction_without_commit() -> Result<(), Box<dyn std::error::Error>> {
-    let root_node = crate::RootNode::<String, String, 10>::new();
-    let mut table = crate::Table {
-        primary: root_node,
-        secondaries: std::collections::HashMap::new(),
-    };
+    let root_node = crate::RootNode::<String, String>::with_fanout(crate::Fanout::new(10, 11));
+    let mut table = super::table(root_node);
 
     let mut transaction = crate::Transaction::new(&mut table);
     assert_eq!(
@@ -23,11 +20,10 @@ fn transaction_without_commit() -> Result<(), Box<dyn std::error::Error>> {
 
 #[test]
 fn transaction_with_commit() -> Result<(), Box<dyn std::error::Error>> {
-    let root_node = crate::RootNode::<String, String, 10>::new();
-    let mut table = crate::Table {
-        primary: root_node,
-        secondaries: std::collections::HashMap::new(),
-    };
+    let folder_path = super::temp_dir("transaction_with_commit");
+    let mut wal = crate::Wal::open(&folder_path)?;
+    let root_node = crate::RootNode::<String, String>::with_fanout(crate::Fanout::new(10, 11));
+    let mut table = super::table(root_node);
 
     let mut transaction = crate::Transaction::new(&mut table);
     assert_eq!(
@@ -37,25 +33,24 @@ fn transaction_with_commit() -> Result<(), Box<dyn std::error::Error>> {
         )))?,
         ()
     );
-    transaction.commit(std::path::Path::new("./data"))?;
+    transaction.commit(&mut wal)?;
 
     let transaction = crate::Transaction::new(&mut table);
     assert_eq!(
         transaction.find(&"key".to_string())?,
         Some("value".to_string())
     );
-    transaction.commit(std::path::Path::new("./data"))?;
+    transaction.commit(&mut wal)?;
 
     Ok(())
 }
 
 #[test]
 fn transaction_update() -> Result<(), Box<dyn std::error::Error>> {
-    let root_node = crate::RootNode::<String, String, 10>::new();
-    let mut table = crate::Table {
-        primary: root_node,
-        secondaries: std::collections::HashMap::new(),
-    };
+    let folder_path = super::temp_dir("transaction_update");
+    let mut wal = crate::Wal::open(&folder_path)?;
+    let root_node = crate::RootNode::<String, String>::with_fanout(crate::Fanout::new(10, 11));
+    let mut table = super::table(root_node);
 
     let mut transaction = crate::Transaction::new(&mut table);
     assert_eq!(
@@ -65,7 +60,7 @@ fn transaction_update() -> Result<(), Box<dyn std::error::Error>> {
         )))?,
         ()
     );
-    transaction.commit(std::path::Path::new("./data"))?;
+    transaction.commit(&mut wal)?;
 
     let mut transaction = crate::Transaction::new(&mut table);
     assert_eq!(
@@ -79,18 +74,17 @@ fn transaction_update() -> Result<(), Box<dyn std::error::Error>> {
         transaction.find(&"key".to_string())?,
         Some("value_updated".to_string())
     );
-    transaction.commit(std::path::Path::new("./data"))?;
+    transaction.commit(&mut wal)?;
 
     Ok(())
 }
 
 #[test]
 fn transaction_remove() -> Result<(), Box<dyn std::error::Error>> {
-    let root_node = crate::RootNode::<String, String, 10>::new();
-    let mut table = crate::Table {
-        primary: root_node,
-        secondaries: std::collections::HashMap::new(),
-    };
+    let folder_path = super::temp_dir("transaction_remove");
+    let mut wal = crate::Wal::open(&folder_path)?;
+    let root_node = crate::RootNode::<String, String>::with_fanout(crate::Fanout::new(10, 11));
+    let mut table = super::table(root_node);
 
     let mut transaction = crate::Transaction::new(&mut table);
     assert_eq!(
@@ -100,7 +94,7 @@ fn transaction_remove() -> Result<(), Box<dyn std::error::Error>> {
         )))?,
         ()
     );
-    transaction.commit(std::path::Path::new("./data"))?;
+    transaction.commit(&mut wal)?;
 
     let mut transaction = crate::Transaction::new(&mut table);
     assert_eq!(
@@ -108,18 +102,17 @@ fn transaction_remove() -> Result<(), Box<dyn std::error::Error>> {
         ()
     );
     assert_eq!(transaction.find(&"key".to_string())?, None);
-    transaction.commit(std::path::Path::new("./data"))?;
+    transaction.commit(&mut wal)?;
 
     Ok(())
 }
 
 #[test]
 fn transaction_many() -> Result<(), Box<dyn std::error::Error>> {
-    let root_node = crate::RootNode::<String, String, 10>::new();
-    let mut table = crate::Table {
-        primary: root_node,
-        secondaries: std::collections::HashMap::new(),
-    };
+    let folder_path = super::temp_dir("transaction_many");
+    let mut wal = crate::Wal::open(&folder_path)?;
+    let root_node = crate::RootNode::<String, String>::with_fanout(crate::Fanout::new(10, 11));
+    let mut table = super::table(root_node);
 
     for i in 0..1000 {
         let key = format!("key{}", i);
@@ -127,7 +120,7 @@ fn transaction_many() -> Result<(), Box<dyn std::error::Error>> {
 
         let mut transaction = crate::Transaction::new(&mut table);
         assert_eq!(transaction.exec(crate::Request::Insert((key, value)))?, ());
-        transaction.commit(std::path::Path::new("./data"))?;
+        transaction.commit(&mut wal)?;
     }
 
     for i in 0..1000 {
@@ -136,7 +129,7 @@ fn transaction_many() -> Result<(), Box<dyn std::error::Error>> {
 
         let transaction = crate::Transaction::new(&mut table);
         assert_eq!(transaction.find(&key)?, Some(value));
-        transaction.commit(std::path::Path::new("./data"))?;
+        transaction.commit(&mut wal)?;
     }
 
     Ok(())
@@ -144,12 +137,10 @@ fn transaction_many() -> Result<(), Box<dyn std::error::Error>> {
 
 #[test]
 fn transaction_persist() -> Result<(), Box<dyn std::error::Error>> {
-    crate::io::remove_dir(std::path::Path::new("./data"))?;
-    let root_node = crate::RootNode::<String, String, 10>::new();
-    let mut table = crate::Table {
-        primary: root_node,
-        secondaries: std::collections::HashMap::new(),
-    };
+    let folder_path = super::temp_dir("transaction_persist");
+    let mut wal = crate::Wal::open(&folder_path)?;
+    let root_node = crate::RootNode::<String, String>::with_fanout(crate::Fanout::new(10, 11));
+    let mut table = super::table(root_node);
 
     for i in 0..100 {
         let key = format!("key{}", i);
@@ -157,9 +148,9 @@ fn transaction_persist() -> Result<(), Box<dyn std::error::Error>> {
 
         let mut transaction = crate::Transaction::new(&mut table);
         assert_eq!(transaction.exec(crate::Request::Insert((key, value)))?, ());
-        transaction.commit(std::path::Path::new("./data"))?;
+        transaction.commit(&mut wal)?;
     }
-    crate::dump(&table.primary, std::path::Path::new("./data"))?;
+    crate::dump(&table.primary, &mut wal)?;
 
     for i in 100..200 {
         let key = format!("key{}", i);
@@ -167,22 +158,108 @@ fn transaction_persist() -> Result<(), Box<dyn std::error::Error>> {
 
         let mut transaction = crate::Transaction::new(&mut table);
         assert_eq!(transaction.exec(crate::Request::Insert((key, value)))?, ());
-        transaction.commit(std::path::Path::new("./data"))?;
+        transaction.commit(&mut wal)?;
     }
 
-    let root_node = crate::load::<String, String, 10>(std::path::Path::new("./data"))?;
-    let mut table = crate::Table {
-        primary: root_node,
-        secondaries: std::collections::HashMap::new(),
-    };
+    let root_node = crate::load::<String, String>(&folder_path)?;
+    let mut table = super::table(root_node);
     for i in 0..200 {
         let key = format!("key{}", i);
         let value = format!("value{}", i);
 
         let transaction = crate::Transaction::new(&mut table);
         assert_eq!(transaction.find(&key)?, Some(value));
-        transaction.commit(std::path::Path::new("./data"))?;
+        transaction.commit(&mut wal)?;
     }
 
     Ok(())
 }
+
+#[test]
+fn transaction_floor_ceiling() -> Result<(), Box<dyn std::error::Error>> {
+    let mut root_node = crate::RootNode::<u32, String>::with_fanout(crate::Fanout::new(10, 11));
+    for i in 1..=10 {
+        root_node.insert(&(i * 10), format!("value{}", i * 10))?;
+    }
+    let mut table = super::table(root_node);
+
+    let mut transaction = crate::Transaction::new(&mut table);
+    transaction.exec(crate::Request::Remove(10))?;
+    transaction.exec(crate::Request::Remove(50))?;
+    transaction.exec(crate::Request::Insert((45, "value45".to_string())))?;
+    transaction.exec(crate::Request::Update((60, "updated".to_string())))?;
+    transaction.exec(crate::Request::Insert((110, "value110".to_string())))?;
+
+    assert_eq!(transaction.first()?, Some((20, "value20".to_string())));
+    assert_eq!(transaction.last()?, Some((110, "value110".to_string())));
+    assert_eq!(transaction.floor(&55)?, Some((45, "value45".to_string())));
+    assert_eq!(transaction.floor(&15)?, None);
+    assert_eq!(transaction.ceiling(&46)?, Some((60, "updated".to_string())));
+    assert_eq!(
+        transaction.ceiling(&101)?,
+        Some((110, "value110".to_string()))
+    );
+
+    assert_eq!(transaction.pop_last()?, Some((110, "value110".to_string())));
+    assert_eq!(transaction.pop_last()?, Some((100, "value100".to_string())));
+    assert_eq!(transaction.pop_first()?, Some((20, "value20".to_string())));
+    assert_eq!(transaction.find(&100)?, None);
+    assert_eq!(transaction.first()?, Some((30, "value30".to_string())));
+
+    Ok(())
+}
+
+#[test]
+fn transaction_collation() -> Result<(), Box<dyn std::error::Error>> {
+    struct Descending;
+
+    impl crate::Comparator<u32> for Descending {
+        fn name(&self) -> &str {
+            "descending"
+        }
+
+        fn compare(&self, first: &u32, second: &u32) -> std::cmp::Ordering {
+            second.cmp(first)
+        }
+    }
+
+    let folder_path = super::temp_dir("transaction_collation");
+    let mut wal = crate::Wal::open(&folder_path)?;
+    let mut root_node = crate::RootNode::<u32, String, _>::with_collation(
+        crate::Fanout::new(4, 5),
+        crate::Collation::new(Descending),
+    );
+    for i in 1..=10 {
+        root_node.insert(&(i * 10), format!("value{}", i * 10))?;
+    }
+    crate::dump(&root_node, &mut wal)?;
+
+    // A table recovered with a collation chosen at run time.
+    let recovery =
+        crate::recover::<u32, String, _>(&folder_path, crate::Collation::new(Descending))?;
+    let mut table = super::table(recovery.root_node);
+
+    let mut transaction = crate::Transaction::new(&mut table);
+    transaction.exec(crate::Request::Remove(100))?;
+    transaction.exec(crate::Request::Insert((45, "value45".to_string())))?;
+    transaction.exec(crate::Request::Insert((5, "value5".to_string())))?;
+
+    assert_eq!(transaction.first()?, Some((90, "value90".to_string())));
+    assert_eq!(transaction.last()?, Some((5, "value5".to_string())));
+    assert_eq!(transaction.floor(&44)?, Some((45, "value45".to_string())));
+    assert_eq!(transaction.ceiling(&44)?, Some((40, "value40".to_string())));
+
+    let page = crate::Cursor::seek(50).next_page(&transaction, 3);
+    let keys: Vec<_> = page.entries.iter().map(|(key, _)| *key).collect();
+    assert_eq!(keys, vec![50, 45, 40]);
+    let page = page.start.prev_page(&transaction, 2);
+    let keys: Vec<_> = page.entries.iter().map(|(key, _)| *key).collect();
+    assert_eq!(keys, vec![70, 60]);
+
+    transaction.commit(&mut wal)?;
+    let keys: Vec<_> = table.snapshot().range(..).map(|(key, _)| *key).collect();
+    assert_eq!(keys, vec![90, 80, 70, 60, 50, 45, 40, 30, 20, 10, 5]);
+
+    std::fs::remove_dir_all(&folder_path)?;
+    Ok(())
+}
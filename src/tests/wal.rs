@@ -0,0 +1,220 @@
+#[cfg(test)]
+fn commit_range(
+    table: &mut crate::Table<u32, String>,
+    wal: &mut crate::Wal,
+    keys: std::ops::Range<u32>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for key in keys {
+        let mut transaction = crate::Transaction::new(table);
+        transaction.exec(crate::Request::Insert((key, format!("value{}", key))))?;
+        transaction.commit(wal)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn wal_segments() -> Result<(), Box<dyn std::error::Error>> {
+    let folder_path = super::temp_dir("wal_segments");
+    let mut table = super::table(crate::RootNode::<u32, String>::with_fanout(
+        crate::Fanout::new(4, 5),
+    ));
+
+    let mut wal = crate::Wal::open(&folder_path)?.with_segment_size(256);
+    crate::dump(&table.primary, &mut wal)?;
+    commit_range(&mut table, &mut wal, 0..50)?;
+    assert_eq!(wal.last_lsn(), 50);
+    assert!(crate::wal::segments(&folder_path.join("commit"))?.len() > 1);
+
+    // Reopening carries on with the sequence numbers.
+    drop(wal);
+    let mut wal = crate::Wal::open(&folder_path)?
+        .with_segment_size(256)
+        .with_format(crate::Format::Json);
+    assert_eq!(wal.last_lsn(), 50);
+    commit_range(&mut table, &mut wal, 50..60)?;
+    assert_eq!(wal.last_lsn(), 60);
+
+    let loaded = crate::load::<u32, String>(&folder_path)?;
+    assert_eq!(loaded, table.primary);
+    Ok(())
+}
+
+#[test]
+fn wal_corruption() -> Result<(), Box<dyn std::error::Error>> {
+    let folder_path = super::temp_dir("wal_corruption");
+    let mut table = super::table(crate::RootNode::<u32, String>::with_fanout(
+        crate::Fanout::new(4, 5),
+    ));
+    let mut wal = crate::Wal::open(&folder_path)?;
+    crate::dump(&table.primary, &mut wal)?;
+    commit_range(&mut table, &mut wal, 0..10)?;
+
+    let (_, segment) = crate::wal::segments(&folder_path.join("commit"))?.remove(0);
+    let mut bytes = std::fs::read(&segment)?;
+    bytes[40] ^= 0xff;
+    std::fs::write(&segment, bytes)?;
+    assert!(crate::load::<u32, String>(&folder_path).is_err());
+    Ok(())
+}
+
+#[test]
+fn wal_corrupted_length() -> Result<(), Box<dyn std::error::Error>> {
+    let folder_path = super::temp_dir("wal_corrupted_length");
+    let mut table = super::table(crate::RootNode::<u32, String>::with_fanout(
+        crate::Fanout::new(4, 5),
+    ));
+    let mut wal = crate::Wal::open(&folder_path)?;
+    commit_range(&mut table, &mut wal, 0..10)?;
+    drop(wal);
+
+    let (_, segment) = crate::wal::segments(&folder_path.join("commit"))?.remove(0);
+    let bytes = std::fs::read(&segment)?;
+    // Segment header, then the length of the first payload and the rest of
+    // its record header.
+    let first_len = u32::from_le_bytes([bytes[5], bytes[6], bytes[7], bytes[8]]) as usize;
+    let second = 5 + 17 + first_len;
+
+    // Whether the length now points within or past the end of the file, the
+    // records after it show that this is not a torn write.
+    for byte in [0, 2] {
+        let mut corrupted = bytes.clone();
+        corrupted[second + byte] ^= 0x01;
+        std::fs::write(&segment, &corrupted)?;
+        assert!(crate::load::<u32, String>(&folder_path).is_err());
+        assert!(crate::Wal::open(&folder_path).is_err());
+        assert_eq!(std::fs::read(&segment)?, corrupted);
+    }
+    Ok(())
+}
+
+#[test]
+fn wal_torn_tail() -> Result<(), Box<dyn std::error::Error>> {
+    let folder_path = super::temp_dir("wal_torn_tail");
+    let mut table = super::table(crate::RootNode::<u32, String>::with_fanout(
+        crate::Fanout::new(4, 5),
+    ));
+    let mut wal = crate::Wal::open(&folder_path)?;
+    crate::dump(&table.primary, &mut wal)?;
+    commit_range(&mut table, &mut wal, 0..10)?;
+    drop(wal);
+
+    // The last commit only partly reached the disk.
+    let (_, segment) = crate::wal::segments(&folder_path.join("commit"))?.remove(0);
+    let len = std::fs::metadata(&segment)?.len();
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(&segment)?
+        .set_len(len - 3)?;
+
+    let recovery = crate::recover::<u32, String, _>(&folder_path, crate::Natural)?;
+    assert_eq!(recovery.root_node.len(), 9);
+    let torn_tail = recovery.torn_tail.ok_or("no torn tail")?;
+    assert_eq!(torn_tail.offset + torn_tail.len, len - 3);
+
+    let mut wal = crate::Wal::open(&folder_path)?;
+    assert!(wal.torn_tail().is_some());
+    assert_eq!(wal.last_lsn(), 9);
+    let mut table = super::table(recovery.root_node);
+    commit_range(&mut table, &mut wal, 9..12)?;
+    assert_eq!(crate::load::<u32, String>(&folder_path)?, table.primary);
+    Ok(())
+}
+
+#[test]
+fn wal_checkpoint() -> Result<(), Box<dyn std::error::Error>> {
+    let folder_path = super::temp_dir("wal_checkpoint");
+    let mut table = super::table(crate::RootNode::<u32, String>::with_fanout(
+        crate::Fanout::new(4, 5),
+    ));
+    let mut wal = crate::Wal::open(&folder_path)?.with_segment_size(256);
+    commit_range(&mut table, &mut wal, 0..50)?;
+    assert!(crate::wal::segments(&folder_path.join("commit"))?.len() > 1);
+
+    // Segments covered by the dump are dropped, and numbering carries on.
+    crate::dump(&table.primary, &mut wal)?;
+    let segments = crate::wal::segments(&folder_path.join("commit"))?;
+    assert_eq!(segments.len(), 1);
+    assert_eq!(segments[0].0, 51);
+
+    commit_range(&mut table, &mut wal, 50..60)?;
+    assert_eq!(wal.last_lsn(), 60);
+    assert_eq!(crate::load::<u32, String>(&folder_path)?, table.primary);
+
+    // A checkpoint taken from a clone leaves later records in the log.
+    let (lsn, token) = wal.begin_checkpoint()?;
+    let snapshot = table.primary.clone();
+    commit_range(&mut table, &mut wal, 60..65)?;
+    crate::checkpoint(&snapshot, &folder_path, lsn, crate::Format::Binary)?;
+    wal.finish_checkpoint(token)?;
+    assert_eq!(crate::load::<u32, String>(&folder_path)?, table.primary);
+
+    // Records between the checkpoint and the first segment left are missing.
+    crate::checkpoint(&snapshot, &folder_path, 40, crate::Format::Binary)?;
+    assert!(crate::load::<u32, String>(&folder_path).is_err());
+    Ok(())
+}
+
+#[test]
+fn wal_idempotent_replay() -> Result<(), Box<dyn std::error::Error>> {
+    let folder_path = super::temp_dir("wal_idempotent_replay");
+    let wal_path = folder_path.join("commit");
+    let mut table = super::table(crate::RootNode::<u32, String>::with_fanout(
+        crate::Fanout::new(4, 5),
+    ));
+    let mut wal = crate::Wal::open(&folder_path)?.with_segment_size(256);
+    commit_range(&mut table, &mut wal, 0..30)?;
+    let mut transaction = crate::Transaction::new(&mut table);
+    transaction.exec(crate::Request::Update((3, "updated".to_string())))?;
+    transaction.exec(crate::Request::Remove(4))?;
+    transaction.commit(&mut wal)?;
+
+    // Crash after the dump was written but before any segment was removed.
+    let (lsn, _token) = wal.begin_checkpoint()?;
+    crate::checkpoint(&table.primary, &folder_path, lsn, crate::Format::Binary)?;
+    drop(wal);
+    let recovery = crate::recover::<u32, String, _>(&folder_path, crate::Natural)?;
+    assert_eq!(recovery.checkpoint, 31);
+    assert_eq!(recovery.skipped, 31);
+    assert_eq!(recovery.root_node, table.primary);
+
+    // Records the dump holds are applied without failing, even when the
+    // dump does not say it holds them.
+    crate::checkpoint(&table.primary, &folder_path, 0, crate::Format::Json)?;
+    let recovery = crate::recover::<u32, String, _>(&folder_path, crate::Natural)?;
+    assert_eq!(recovery.skipped, 0);
+    assert_eq!(recovery.root_node, table.primary);
+
+    // Segments past the checkpoint are still replayed.
+    let mut wal = crate::Wal::open(&folder_path)?.with_segment_size(256);
+    crate::checkpoint(&table.primary, &folder_path, lsn, crate::Format::Binary)?;
+    commit_range(&mut table, &mut wal, 30..40)?;
+    assert!(crate::wal::segments(&wal_path)?.len() > 1);
+    let recovery = crate::recover::<u32, String, _>(&folder_path, crate::Natural)?;
+    assert_eq!(recovery.skipped, 31);
+    assert_eq!(recovery.root_node, table.primary);
+    Ok(())
+}
+
+#[test]
+fn wal_stray_files() -> Result<(), Box<dyn std::error::Error>> {
+    let folder_path = super::temp_dir("wal_stray_files");
+    let wal_path = folder_path.join("commit");
+    let mut table = super::table(crate::RootNode::<u32, String>::with_fanout(
+        crate::Fanout::new(4, 5),
+    ));
+    let mut wal = crate::Wal::open(&folder_path)?;
+    crate::dump(&table.primary, &mut wal)?;
+    commit_range(&mut table, &mut wal, 0..10)?;
+
+    // Files of other programs are neither replayed nor removed.
+    std::fs::write(wal_path.join(".DS_Store"), b"finder")?;
+    std::fs::write(wal_path.join("1234.json.swp"), b"editor")?;
+    std::fs::create_dir(wal_path.join("5678.json"))?;
+    assert_eq!(crate::load::<u32, String>(&folder_path)?, table.primary);
+    crate::dump(&table.primary, &mut wal)?;
+    assert!(wal_path.join(".DS_Store").exists());
+    assert!(wal_path.join("1234.json.swp").exists());
+    assert!(wal_path.join("5678.json").is_dir());
+    assert_eq!(crate::load::<u32, String>(&folder_path)?, table.primary);
+    Ok(())
+}
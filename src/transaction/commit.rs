@@ -1,15 +1,17 @@
 use super::{Transaction, TransactionError, Write};
-use crate::Node;
+use crate::{wal::Wal, Comparator, Node};
 use serde::Serialize;
-use std::{error::Error, fmt, hash::Hash, path::Path};
+use std::{error::Error, fmt, hash::Hash};
 
-impl<K, V, const N: usize> Transaction<'_, K, V, N>
+impl<K, V, C> Transaction<'_, K, V, C>
 where
     K: 'static + fmt::Debug + Clone + Serialize + Hash + Ord,
     V: 'static + fmt::Debug + Clone + Serialize,
+    C: Comparator<K> + Clone,
 {
-    pub fn commit(self, folder_path: &Path) -> Result<(), Box<dyn Error>> {
-        if self.write_set.len() != 0 {
+    /// Writes the changes to `wal` and then applies them to the table.
+    pub fn commit(self, wal: &mut Wal) -> Result<(), Box<dyn Error>> {
+        if !self.write_set.is_empty() {
             for (key, w) in self.write_set.iter() {
                 match self.table.primary.find(key) {
                     Some(_) => match w {
@@ -23,7 +25,7 @@ where
                 }?;
             }
 
-            self.write_log(folder_path)?;
+            self.write_log(wal)?;
 
             for (primary_key, w) in self.write_set.into_iter() {
                 match w {
@@ -1,12 +1,13 @@
 use super::{Request, Transaction, Write};
-use crate::Node;
+use crate::{Comparator, Node};
 use core::hash::Hash;
 use std::{error::Error, fmt};
 
-impl<K, V, const N: usize> Transaction<'_, K, V, N>
+impl<K, V, C> Transaction<'_, K, V, C>
 where
     K: 'static + fmt::Debug + Clone + Hash + Ord,
     V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone,
 {
     pub fn exec(&mut self, req: Request<K, V>) -> Result<(), Box<dyn Error>> {
         match req {
@@ -24,7 +25,7 @@ where
                         }
                     }
                 } else {
-                    if let Some(_) = self.table.primary.find(&key) {
+                    if self.table.primary.find(&key).is_some() {
                         self.write_set.insert(key, Write::Update(value));
                     } else {
                         self.write_set.insert(key, Write::Insert(value));
@@ -58,4 +59,20 @@ where
         }
         Ok(())
     }
+
+    pub fn pop_first(&mut self) -> Result<Option<(K, V)>, Box<dyn Error>> {
+        let first = self.first()?;
+        if let Some((key, _)) = &first {
+            self.exec(Request::Remove(key.clone()))?;
+        }
+        Ok(first)
+    }
+
+    pub fn pop_last(&mut self) -> Result<Option<(K, V)>, Box<dyn Error>> {
+        let last = self.last()?;
+        if let Some((key, _)) = &last {
+            self.exec(Request::Remove(key.clone()))?;
+        }
+        Ok(last)
+    }
 }
@@ -1,7 +1,14 @@
-use crate::io;
 use crate::table::Table;
+use crate::wal::Wal;
+use crate::{Comparator, Natural};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, error::Error, fmt, hash::Hash, path::Path};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    hash::Hash,
+    ops::{Bound, RangeBounds},
+};
 
 mod commit;
 mod exec;
@@ -19,13 +26,13 @@ pub enum TransactionError {
     Unknown,
 }
 
-pub struct Transaction<'a, K, V, const N: usize>
+pub struct Transaction<'a, K, V, C = Natural>
 where
     K: fmt::Debug,
     V: fmt::Debug,
 {
     write_set: HashMap<K, Write<V>>,
-    table: &'a mut Table<K, V, N>,
+    table: &'a mut Table<K, V, C>,
 }
 
 pub enum Request<K, V> {
@@ -41,26 +48,44 @@ pub enum Write<V> {
     Remove,
 }
 
-#[derive(Serialize, Deserialize)]
-pub enum WriteSecondary {
-    InsertTo,
-    RemoveFrom,
-}
-
-impl<K, V, const N: usize> Transaction<'_, K, V, N>
+impl<K, V, C> Transaction<'_, K, V, C>
 where
     K: 'static + fmt::Debug + Clone + Serialize + Hash + Ord,
     V: 'static + fmt::Debug + Clone + Serialize,
+    C: Comparator<K> + Clone,
 {
-    pub fn new(table: &mut Table<K, V, N>) -> Transaction<K, V, N> {
+    pub fn new(table: &mut Table<K, V, C>) -> Transaction<'_, K, V, C> {
         let write_set = HashMap::new();
         Transaction { write_set, table }
     }
 
     pub fn abort(self) {}
 
-    fn write_log(&self, folder_path: &Path) -> Result<(), Box<dyn Error>> {
-        io::dump(&folder_path.join(crate::WAL_FOLDER_PATH), &self.write_set)?;
+    fn write_log(&self, wal: &mut Wal) -> Result<(), Box<dyn Error>> {
+        wal.append(&self.write_set)?;
         Ok(())
     }
 }
+
+impl<K, V, C> Transaction<'_, K, V, C>
+where
+    K: 'static + fmt::Debug + Clone + Ord,
+    V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone,
+{
+    /// Whether `key` is within `bounds` by the collation of the table.
+    fn within(&self, bounds: (Bound<&K>, Bound<&K>), key: &K) -> bool {
+        let collation = self.table.primary.collation();
+        let after_start = match bounds.start_bound() {
+            Bound::Included(start) => collation.le(start, key),
+            Bound::Excluded(start) => collation.lt(start, key),
+            Bound::Unbounded => true,
+        };
+        let before_end = match bounds.end_bound() {
+            Bound::Included(end) => collation.le(key, end),
+            Bound::Excluded(end) => collation.lt(key, end),
+            Bound::Unbounded => true,
+        };
+        after_start && before_end
+    }
+}
@@ -1,11 +1,12 @@
 use super::{Transaction, TransactionError, Write};
-use crate::{table::Primitive, Node};
-use std::{collections::HashSet, error::Error, fmt, hash::Hash};
+use crate::{cursor::Paginate, table::Primitive, Comparator, Node};
+use std::{collections::HashSet, error::Error, fmt, hash::Hash, ops::Bound};
 
-impl<K, V, const N: usize> Transaction<'_, K, V, N>
+impl<K, V, C> Transaction<'_, K, V, C>
 where
     K: 'static + fmt::Debug + Clone + Hash + Ord,
     V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone,
 {
     pub fn find(&self, key: &K) -> Result<Option<V>, Box<dyn Error>> {
         Ok(if let Some(w) = self.write_set.get(key) {
@@ -15,13 +16,76 @@ where
                 Write::Remove => None,
             }
         } else {
-            match self.table.primary.find(key) {
-                Some(value) => Some(value.clone()),
-                None => None,
-            }
+            self.table.primary.find(key).cloned()
         })
     }
 
+    pub fn first(&self) -> Result<Option<(K, V)>, Box<dyn Error>> {
+        Ok(self.seek((Bound::Unbounded, Bound::Unbounded), false))
+    }
+
+    pub fn last(&self) -> Result<Option<(K, V)>, Box<dyn Error>> {
+        Ok(self.seek((Bound::Unbounded, Bound::Unbounded), true))
+    }
+
+    /// Last entry whose key is at or before `key` in the order of the table.
+    pub fn floor(&self, key: &K) -> Result<Option<(K, V)>, Box<dyn Error>> {
+        Ok(self.seek((Bound::Unbounded, Bound::Included(key)), true))
+    }
+
+    /// First entry whose key is at or after `key` in the order of the table.
+    pub fn ceiling(&self, key: &K) -> Result<Option<(K, V)>, Box<dyn Error>> {
+        Ok(self.seek((Bound::Included(key), Bound::Unbounded), false))
+    }
+
+    /// First entry within `bounds`, or the last one if `reverse` is set, as
+    /// seen through the pending writes of this transaction.
+    fn seek(&self, bounds: (Bound<&K>, Bound<&K>), reverse: bool) -> Option<(K, V)> {
+        let mut committed = self.table.primary.range(bounds);
+        let committed = loop {
+            let next = if reverse {
+                committed.next_back()
+            } else {
+                committed.next()
+            };
+            match next {
+                Some((key, value)) => match self.write_set.get(key) {
+                    Some(Write::Insert(value)) | Some(Write::Update(value)) => {
+                        break Some((key, value))
+                    }
+                    Some(Write::Remove) => {}
+                    None => break Some((key, value)),
+                },
+                None => break None,
+            }
+        };
+
+        let pending = self.write_set.iter().filter_map(|(key, w)| match w {
+            Write::Insert(value) | Write::Update(value) if self.within(bounds, key) => {
+                Some((key, value))
+            }
+            _ => None,
+        });
+        let collation = self.table.primary.collation();
+        let pending = if reverse {
+            pending.max_by(|a, b| collation.compare(a.0, b.0))
+        } else {
+            pending.min_by(|a, b| collation.compare(a.0, b.0))
+        };
+
+        let found = match (committed, pending) {
+            (Some(committed), Some(pending)) => {
+                if collation.lt(committed.0, pending.0) == reverse {
+                    pending
+                } else {
+                    committed
+                }
+            }
+            (committed, pending) => committed.or(pending)?,
+        };
+        Some((found.0.clone(), found.1.clone()))
+    }
+
     pub fn select(&self, index: &String, key: &Primitive) -> Result<HashSet<K>, Box<dyn Error>> {
         let index = self
             .table
@@ -33,14 +97,10 @@ where
             Err(TransactionError::IllegalKeyType)?;
         }
 
-        let mut primary_keys = if let Some(primary_keys) = index.find(key) {
-            primary_keys.to_owned()
-        } else {
-            HashSet::new()
-        };
+        let mut primary_keys: HashSet<K> = index.find(key).cloned().collect();
 
         primary_keys.retain(|primary_key| {
-            if let Some(w) = self.write_set.get(&primary_key) {
+            if let Some(w) = self.write_set.get(primary_key) {
                 match w {
                     Write::Insert(value) | Write::Update(value)
                         if &index.select(value.clone()) != key =>
@@ -69,3 +129,73 @@ where
         Ok(primary_keys)
     }
 }
+
+impl<K, V, C> Paginate<K, V> for Transaction<'_, K, V, C>
+where
+    K: 'static + fmt::Debug + Clone + Hash + Ord,
+    V: 'static + fmt::Debug + Clone,
+    C: Comparator<K> + Clone,
+{
+    /// Pages through the table as seen through the pending writes of this
+    /// transaction. The pending writes within the bounds are sorted once and
+    /// merged with a single scan of the table.
+    fn page_within(
+        &self,
+        bounds: (Bound<&K>, Bound<&K>),
+        size: usize,
+        reverse: bool,
+    ) -> Vec<(K, V)> {
+        let mut pending: Vec<(&K, &V)> = self
+            .write_set
+            .iter()
+            .filter_map(|(key, w)| match w {
+                Write::Insert(value) | Write::Update(value) if self.within(bounds, key) => {
+                    Some((key, value))
+                }
+                _ => None,
+            })
+            .collect();
+        let collation = self.table.primary.collation();
+        pending.sort_unstable_by(|a, b| {
+            if reverse {
+                collation.compare(b.0, a.0)
+            } else {
+                collation.compare(a.0, b.0)
+            }
+        });
+        let mut pending = pending.into_iter().peekable();
+
+        // Rows with a pending write are seen through the write instead.
+        let committed = self.table.primary.range(bounds);
+        let committed: Box<dyn Iterator<Item = (&K, &V)>> = if reverse {
+            Box::new(committed.rev())
+        } else {
+            committed
+        };
+        let mut committed = committed
+            .filter(|(key, _)| !self.write_set.contains_key(key))
+            .peekable();
+
+        let mut entries: Vec<(K, V)> = Vec::with_capacity(size);
+        while entries.len() < size {
+            let take_pending = match (committed.peek(), pending.peek()) {
+                (Some(committed), Some(pending)) => collation.lt(pending.0, committed.0) != reverse,
+                (None, Some(_)) => true,
+                (_, None) => false,
+            };
+            let next = if take_pending {
+                pending.next()
+            } else {
+                committed.next()
+            };
+            match next {
+                Some((key, value)) => entries.push((key.clone(), value.clone())),
+                None => break,
+            }
+        }
+        if reverse {
+            entries.reverse();
+        }
+        entries
+    }
+}
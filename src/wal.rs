@@ -0,0 +1,433 @@
+use crate::io::{self, Format};
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    convert::TryFrom,
+    error::Error,
+    fs,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// First bytes of every log segment, followed by the format version.
+const MAGIC: [u8; 4] = *b"DBWL";
+const VERSION: u8 = 1;
+const SEGMENT_HEADER_LEN: u64 = MAGIC.len() as u64 + 1;
+/// Payload length, checksum, sequence number and payload format.
+const RECORD_HEADER_LEN: u64 = 4 + 4 + 8 + 1;
+const EXTENSION: &str = "wal";
+const DEFAULT_SEGMENT_SIZE: u64 = 16 * 1024 * 1024;
+
+#[derive(thiserror::Error, Debug)]
+pub enum WalError {
+    #[error("{0:?} is not a log segment")]
+    NotASegment(PathBuf),
+    #[error("log format version {0} is not supported")]
+    UnsupportedVersion(u8),
+    #[error("record at offset {offset} of {path:?} is corrupted")]
+    Corrupted { path: PathBuf, offset: u64 },
+    #[error("record {found} follows record {previous}")]
+    OutOfOrder { previous: u64, found: u64 },
+}
+
+/// Bytes at the end of the log left by a write that a crash cut short.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TornTail {
+    /// Segment the bytes were found in.
+    pub path: PathBuf,
+    /// Position in the segment the record started at.
+    pub offset: u64,
+    pub len: u64,
+}
+
+/// Checkpoint begun by `Wal::begin_checkpoint`. Dropping it without finishing
+/// the checkpoint only leaves the log longer than it needs to be.
+#[derive(Debug)]
+#[must_use = "segments are only removed once the checkpoint is finished"]
+pub struct CheckpointToken {
+    lsn: u64,
+}
+
+/// A record read back from the log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    /// Log sequence number, increasing by one with every record.
+    pub lsn: u64,
+    pub format: Format,
+    pub payload: Vec<u8>,
+}
+
+/// Append-only write-ahead log kept as a series of segment files in the
+/// `commit` folder. Every record is prefixed with its length, a CRC-32 of the
+/// whole record and its log sequence number, and each segment is named after
+/// the sequence number of its first record. A new segment is started once the
+/// current one would grow beyond the segment size.
+pub struct Wal {
+    folder_path: PathBuf,
+    segment_size: u64,
+    format: Format,
+    segment: fs::File,
+    segment_len: u64,
+    next_lsn: u64,
+    torn_tail: Option<TornTail>,
+}
+
+impl Wal {
+    /// Opens the log of the database in `folder_path`, appending to its last
+    /// segment.
+    pub fn open(folder_path: &Path) -> Result<Self, Box<dyn Error>> {
+        let wal_path = folder_path.join(crate::WAL_FOLDER_PATH);
+        fs::create_dir_all(&wal_path)?;
+
+        let mut torn_tail = None;
+        let (segment, next_lsn) = match segments(&wal_path)?.pop() {
+            Some((first_lsn, path)) => {
+                let (records, tail) = read_segment(&path, first_lsn, true)?;
+                let next_lsn = records.last().map_or(first_lsn, |record| record.lsn + 1);
+                let segment = match &tail {
+                    // Not even the header made it to the disk.
+                    Some(tail) if tail.offset == 0 => {
+                        fs::remove_file(&path)?;
+                        create_segment(&wal_path, first_lsn)?
+                    }
+                    Some(tail) => {
+                        let segment = fs::OpenOptions::new().append(true).open(&path)?;
+                        segment.set_len(tail.offset)?;
+                        segment.sync_all()?;
+                        segment
+                    }
+                    None => fs::OpenOptions::new().append(true).open(&path)?,
+                };
+                torn_tail = tail;
+                (segment, next_lsn)
+            }
+            None => (create_segment(&wal_path, 1)?, 1),
+        };
+        // A commit file of an older version torn by a crash goes as a whole.
+        if let Some(path) = legacy_files(&wal_path)?.pop() {
+            if let Some(tail) = torn_legacy_file(&path)? {
+                fs::remove_file(&path)?;
+                io::sync_dir(&wal_path)?;
+                torn_tail = torn_tail.or(Some(tail));
+            }
+        }
+        Ok(Wal {
+            folder_path: folder_path.to_path_buf(),
+            segment_size: DEFAULT_SEGMENT_SIZE,
+            format: Format::default(),
+            segment_len: segment.metadata()?.len(),
+            segment,
+            next_lsn,
+            torn_tail,
+        })
+    }
+
+    /// Torn record cut off the end of the log when it was opened, if any.
+    pub fn torn_tail(&self) -> Option<&TornTail> {
+        self.torn_tail.as_ref()
+    }
+
+    /// Sets the size beyond which no more records are added to a segment.
+    /// A record larger than this still gets a segment of its own.
+    pub fn with_segment_size(mut self, segment_size: u64) -> Self {
+        self.segment_size = segment_size;
+        self
+    }
+
+    /// Sets the format new records are encoded in.
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Folder of the database this log belongs to.
+    pub fn folder_path(&self) -> &Path {
+        &self.folder_path
+    }
+
+    /// Sequence number of the last record written, or 0 if there is none.
+    pub fn last_lsn(&self) -> u64 {
+        self.next_lsn - 1
+    }
+
+    /// Appends `value` as a new record and syncs it to disk, returning its
+    /// sequence number.
+    pub fn append<T>(&mut self, value: &T) -> Result<u64, Box<dyn Error>>
+    where
+        T: ?Sized + Serialize,
+    {
+        let payload = self.format.encode(value)?;
+        let record_len = RECORD_HEADER_LEN + payload.len() as u64;
+        if self.segment_len > SEGMENT_HEADER_LEN
+            && self.segment_len + record_len > self.segment_size
+        {
+            self.segment = create_segment(&self.wal_path(), self.next_lsn)?;
+            self.segment_len = SEGMENT_HEADER_LEN;
+        }
+
+        let lsn = self.next_lsn;
+        let mut record = Vec::with_capacity(record_len as usize);
+        record.write_u32::<LittleEndian>(u32::try_from(payload.len())?)?;
+        record.write_u32::<LittleEndian>(0)?;
+        record.write_u64::<LittleEndian>(lsn)?;
+        record.write_u8(self.format.tag())?;
+        record.extend_from_slice(&payload);
+        let checksum = checksum(&record);
+        LittleEndian::write_u32(&mut record[4..8], checksum);
+
+        self.segment.write_all(&record)?;
+        self.segment.sync_data()?;
+        self.segment_len += record_len;
+        self.next_lsn += 1;
+        Ok(lsn)
+    }
+
+    /// Begins a checkpoint at the last record written so far, returning its
+    /// sequence number along with a token to finish the checkpoint with.
+    /// Commits may go on being appended in the meantime.
+    ///
+    /// A new segment is started unless the current one is still empty, so
+    /// that every record up to the checkpoint lies in segments that can be
+    /// removed as a whole.
+    pub fn begin_checkpoint(&mut self) -> Result<(u64, CheckpointToken), Box<dyn Error>> {
+        if self.segment_len > SEGMENT_HEADER_LEN {
+            self.segment = create_segment(&self.wal_path(), self.next_lsn)?;
+            self.segment_len = SEGMENT_HEADER_LEN;
+        }
+        let lsn = self.last_lsn();
+        Ok((lsn, CheckpointToken { lsn }))
+    }
+
+    /// Finishes the checkpoint of `token` once a dump holding every record up
+    /// to it is on disk, by removing the segments holding no record after it,
+    /// along with the per-commit files of older versions. The segment written
+    /// to is kept.
+    pub fn finish_checkpoint(&mut self, token: CheckpointToken) -> Result<(), Box<dyn Error>> {
+        let wal_path = self.wal_path();
+        for path in legacy_files(&wal_path)? {
+            fs::remove_file(path)?;
+        }
+        let segments = segments(&wal_path)?;
+        for pair in segments.windows(2) {
+            let ((_, path), (next_first_lsn, _)) = (&pair[0], &pair[1]);
+            if *next_first_lsn <= token.lsn + 1 {
+                fs::remove_file(path)?;
+            }
+        }
+        io::sync_dir(&wal_path)
+    }
+
+    fn wal_path(&self) -> PathBuf {
+        self.folder_path.join(crate::WAL_FOLDER_PATH)
+    }
+}
+
+fn segment_path(wal_path: &Path, first_lsn: u64) -> PathBuf {
+    wal_path.join(format!("{:020}.{}", first_lsn, EXTENSION))
+}
+
+fn create_segment(wal_path: &Path, first_lsn: u64) -> Result<fs::File, Box<dyn Error>> {
+    let mut segment = fs::OpenOptions::new()
+        .create_new(true)
+        .append(true)
+        .open(segment_path(wal_path, first_lsn))?;
+    segment.write_all(&MAGIC)?;
+    segment.write_u8(VERSION)?;
+    segment.sync_all()?;
+    io::sync_dir(wal_path)?;
+    Ok(segment)
+}
+
+/// Files of one commit each in `wal_path`, as written before the log was
+/// segmented, in the order they were written. They are named after the time
+/// they were written at in nanoseconds; anything else in the folder, such as
+/// files other programs leave behind, is left out.
+pub fn legacy_files(wal_path: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(wal_path)? {
+        let entry = entry?;
+        let path = entry.path();
+        let written_at = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse::<u128>().ok());
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        if let (Some(written_at), Some("json" | "bin")) = (written_at, extension) {
+            if entry.file_type()?.is_file() {
+                files.push((written_at, path));
+            }
+        }
+    }
+    files.sort();
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Reads the write sets in the files of one commit each in `wal_path`, in
+/// order. The last of them is returned as a torn tail instead if a crash cut
+/// it short, as it was never reported committed; any other one is corrupted.
+pub fn legacy_records<T>(wal_path: &Path) -> Result<(Vec<T>, Option<TornTail>), Box<dyn Error>>
+where
+    T: DeserializeOwned,
+{
+    let paths = legacy_files(wal_path)?;
+    let mut write_sets = Vec::new();
+    for (idx, path) in paths.iter().enumerate() {
+        if idx + 1 == paths.len() {
+            if let Some(torn_tail) = torn_legacy_file(path)? {
+                return Ok((write_sets, Some(torn_tail)));
+            }
+        }
+        write_sets.push(io::load(path)?);
+    }
+    Ok((write_sets, None))
+}
+
+/// The file of one commit at `path` as a torn tail, if it was only partly
+/// written.
+fn torn_legacy_file(path: &Path) -> Result<Option<TornTail>, Box<dyn Error>> {
+    match io::read(path) {
+        Err(e) if io::is_torn(&*e) => Ok(Some(TornTail {
+            path: path.to_path_buf(),
+            offset: 0,
+            len: fs::metadata(path)?.len(),
+        })),
+        _ => Ok(None),
+    }
+}
+
+/// Segments in `wal_path` with the sequence numbers they start at, in order.
+/// Files of other kinds are left out.
+pub fn segments(wal_path: &Path) -> Result<Vec<(u64, PathBuf)>, Box<dyn Error>> {
+    let mut segments = Vec::new();
+    for entry in fs::read_dir(wal_path)? {
+        let path = entry?.path();
+        if path.extension().and_then(|extension| extension.to_str()) != Some(EXTENSION) {
+            continue;
+        }
+        let first_lsn = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse().ok())
+            .ok_or_else(|| WalError::NotASegment(path.clone()))?;
+        segments.push((first_lsn, path));
+    }
+    segments.sort();
+    Ok(segments)
+}
+
+/// CRC-32 of a whole record but the checksum field itself, so that a damaged
+/// length is caught as well as a damaged payload.
+fn checksum(record: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&record[..4]);
+    hasher.update(&record[8..]);
+    hasher.finalize()
+}
+
+/// Record starting at `offset` of `bytes`, if the file holds all of it and
+/// its checksum matches.
+fn valid_record(bytes: &[u8], offset: usize) -> Option<&[u8]> {
+    let header = bytes.get(offset..offset + RECORD_HEADER_LEN as usize)?;
+    let end = offset + RECORD_HEADER_LEN as usize + LittleEndian::read_u32(header) as usize;
+    let record = bytes.get(offset..end)?;
+    if checksum(record) == LittleEndian::read_u32(&header[4..8]) {
+        Some(record)
+    } else {
+        None
+    }
+}
+
+/// Reads every record of the segment at `path`, which starts at `first_lsn`.
+/// If `is_last` is set, a record cut short by the end of the file is taken
+/// for a write torn by a crash and returned apart from the records, as it was
+/// never reported committed. Anywhere else it is corruption.
+pub fn read_segment(
+    path: &Path,
+    first_lsn: u64,
+    is_last: bool,
+) -> Result<(Vec<Record>, Option<TornTail>), Box<dyn Error>> {
+    let mut bytes = Vec::new();
+    fs::File::open(path)?.read_to_end(&mut bytes)?;
+    let torn_tail = |offset: usize| TornTail {
+        path: path.to_path_buf(),
+        offset: offset as u64,
+        len: (bytes.len() - offset) as u64,
+    };
+    if bytes.len() < SEGMENT_HEADER_LEN as usize && is_last && MAGIC.starts_with(&bytes) {
+        return Ok((Vec::new(), Some(torn_tail(0))));
+    }
+    if !bytes.starts_with(&MAGIC) || bytes.len() < SEGMENT_HEADER_LEN as usize {
+        return Err(Box::new(WalError::NotASegment(path.to_path_buf())));
+    }
+    let version = bytes[MAGIC.len()];
+    if version != VERSION {
+        return Err(Box::new(WalError::UnsupportedVersion(version)));
+    }
+
+    let mut records = Vec::new();
+    let mut offset = SEGMENT_HEADER_LEN as usize;
+    while offset < bytes.len() {
+        let mut record = match valid_record(&bytes, offset) {
+            Some(record) => record,
+            None => {
+                // A crash only cuts short the record it was writing, which
+                // then runs up to or past the end of the file with nothing
+                // after it. A damaged length or checksum in the middle of the
+                // segment leaves complete records behind instead.
+                let end = bytes.get(offset..offset + 4).map(|len| {
+                    offset + RECORD_HEADER_LEN as usize + LittleEndian::read_u32(len) as usize
+                });
+                let torn = end.is_none_or(|end| end >= bytes.len())
+                    && (offset + 1..bytes.len()).all(|next| valid_record(&bytes, next).is_none());
+                if is_last && torn {
+                    return Ok((records, Some(torn_tail(offset))));
+                }
+                return Err(Box::new(WalError::Corrupted {
+                    path: path.to_path_buf(),
+                    offset: offset as u64,
+                }));
+            }
+        };
+        offset += record.len();
+        record = &record[8..];
+
+        let lsn = record.read_u64::<LittleEndian>()?;
+        let expected = records
+            .last()
+            .map_or(first_lsn, |record: &Record| record.lsn + 1);
+        if lsn != expected {
+            return Err(Box::new(WalError::OutOfOrder {
+                previous: expected.saturating_sub(1),
+                found: lsn,
+            }));
+        }
+        records.push(Record {
+            lsn,
+            format: Format::from_tag(record.read_u8()?)?,
+            payload: record.to_vec(),
+        });
+    }
+    Ok((records, None))
+}
+
+/// Reads the records of every segment in `wal_path` in order, along with the
+/// torn tail of the last segment if there is one.
+pub fn records(wal_path: &Path) -> Result<(Vec<Record>, Option<TornTail>), Box<dyn Error>> {
+    let segments = segments(wal_path)?;
+    let mut records: Vec<Record> = Vec::new();
+    let mut torn_tail = None;
+    for (idx, (first_lsn, path)) in segments.iter().enumerate() {
+        if let Some(previous) = records.last() {
+            if *first_lsn != previous.lsn + 1 {
+                return Err(Box::new(WalError::OutOfOrder {
+                    previous: previous.lsn,
+                    found: *first_lsn,
+                }));
+            }
+        }
+        let (segment, tail) = read_segment(path, *first_lsn, idx + 1 == segments.len())?;
+        records.extend(segment);
+        torn_tail = tail;
+    }
+    Ok((records, torn_tail))
+}